@@ -0,0 +1,431 @@
+use std::{
+    fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+};
+#[cfg(feature = "archive")]
+use std::{
+    io::{Seek, SeekFrom, Write},
+    path::Component,
+};
+
+#[cfg(feature = "archive")]
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+#[cfg(feature = "archive")]
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::job::Progress;
+
+/// The kinds of archives that can be read and written, with the `archive` feature
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+impl Format {
+
+    /// Picks the format from the extension of an archive name
+    pub fn detect(name: &str) -> Option<Format> {
+        let name: String = name.to_lowercase();
+        if name.ends_with(".zip") {
+            Some(Format::Zip)
+        }
+        else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(Format::TarGz)
+        }
+        else if name.ends_with(".tar") {
+            Some(Format::Tar)
+        }
+        else {
+            None
+        }
+    }
+
+    /// Recognizes an archive from its extension, its first bytes have the final say
+    /// (a `.tar.gz` that is actually a zip gets read as a zip)
+    pub fn of_file(path: &Path) -> Option<Format> {
+        let by_name: Format = Format::detect(&path.file_name()?.to_string_lossy())?;
+        let mut head: Vec<u8> = vec![];
+        fs::File::open(path).ok()?.take(512).read_to_end(&mut head).ok()?;
+        if head.starts_with(b"PK\x03\x04") || head.starts_with(b"PK\x05\x06") {
+            Some(Format::Zip)
+        }
+        else if head.starts_with(&[0x1f, 0x8b]) {
+            Some(Format::TarGz)
+        }
+        else if head.get(257..262) == Some(b"ustar") {
+            Some(Format::Tar)
+        }
+        else {
+            // Unknown contents, such as old tar archives, are trusted to match their name
+            Some(by_name)
+        }
+    }
+
+    /// The name of an archive without its extension
+    fn stem(self, name: &str) -> &str {
+        let lower: String = name.to_lowercase();
+        let ext: &[&str] = match self {
+            Format::Zip => &[".zip"],
+            Format::Tar => &[".tar"],
+            Format::TarGz => &[".tar.gz", ".tgz"],
+        };
+        match ext.iter().find(|ext| lower.ends_with(*ext)) {
+            Some(ext) if name.len() > ext.len() => &name[..name.len()-ext.len()],
+            _ => name,
+        }
+    }
+
+}
+
+/// Counts `path` and everything under it, symbolic links aren't followed
+pub fn count_entries(path: &Path) -> usize {
+    let is_dir: bool = fs::symlink_metadata(path).is_ok_and(|meta| meta.is_dir());
+    1 + if is_dir {
+        fs::read_dir(path)
+            .map(|entries| entries.flatten().map(|entry| count_entries(&entry.path())).sum())
+            .unwrap_or(0)
+    }
+    else {
+        0
+    }
+}
+
+/// Adds `entry` to the archive at `archive`, which is created if it doesn't exist yet,
+/// each entry written is counted in `progress`
+///
+/// Entries are stored relative to the folder containing `entry`
+#[cfg(feature = "archive")]
+pub fn add(archive: &Path, entry: &Path, format: Format, progress: &Progress) -> io::Result<()> {
+    let Some(name) = entry.file_name() else {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "nothing to archive"));
+    };
+    let name: String = name.to_string_lossy().to_string();
+    let exists: bool = archive.exists();
+    if format == Format::TarGz && exists {
+        return Err(io::Error::new(io::ErrorKind::AlreadyExists, "compressed tar archives cannot be added to"));
+    }
+    let file: fs::File = fs::OpenOptions::new().read(true).write(true).create(true).truncate(false).open(archive)?;
+    let result: io::Result<()> = match format {
+        Format::Zip => add_zip(file, exists, entry, &name, progress),
+        Format::Tar => add_tar(file, exists, entry, &name, progress),
+        Format::TarGz => {
+            let mut builder = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+            append_tar(&mut builder, entry, &name, progress).and_then(|()| builder.into_inner()?.finish().map(drop))
+        }
+    };
+    // A failed archive is incomplete, unless it was only added to
+    if !exists && result.is_err() {
+        let _ = fs::remove_file(archive);
+    }
+    result
+}
+
+#[cfg(not(feature = "archive"))]
+pub fn add(_archive: &Path, _entry: &Path, _format: Format, _progress: &Progress) -> io::Result<()> {
+    Err(unsupported())
+}
+
+/// Calls `visit` on `path` and everything under it, folders before what they contain,
+/// `name` is what `path` is called in the archive, symbolic links aren't followed
+#[cfg(feature = "archive")]
+fn walk(path: &Path, name: &str, progress: &Progress, visit: &mut dyn FnMut(&Path, &str, &fs::Metadata) -> io::Result<()>) -> io::Result<()> {
+    if progress.is_cancelled() {
+        return Err(cancelled());
+    }
+    let meta: fs::Metadata = fs::symlink_metadata(path)?;
+    visit(path, name, &meta)?;
+    progress.step();
+    if meta.is_dir() {
+        let mut children: Vec<fs::DirEntry> = fs::read_dir(path)?.collect::<io::Result<_>>()?;
+        children.sort_by_key(fs::DirEntry::file_name);
+        for child in children {
+            walk(&child.path(), &format!("{}/{}", name, child.file_name().to_string_lossy()), progress, visit)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(feature = "archive")]
+fn add_zip(file: fs::File, exists: bool, entry: &Path, name: &str, progress: &Progress) -> io::Result<()> {
+    let mut zip: ZipWriter<fs::File> = if exists { ZipWriter::new_append(file)? } else { ZipWriter::new(file) };
+    walk(entry, name, progress, &mut |path, name, meta| {
+        let mut options: SimpleFileOptions = SimpleFileOptions::default()
+            .compression_method(CompressionMethod::Deflated)
+            .large_file(meta.len() > u32::MAX as u64);
+        // Zip archives store local times, without a time zone
+        let modified = meta.modified().ok()
+            .and_then(|time| zip::DateTime::try_from(chrono::DateTime::<chrono::Local>::from(time).naive_local()).ok());
+        if let Some(modified) = modified {
+            options = options.last_modified_time(modified);
+        }
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            options = options.unix_permissions(meta.permissions().mode());
+        }
+        if meta.is_dir() {
+            zip.add_directory(name, options)?;
+        }
+        else if meta.is_symlink() {
+            zip.add_symlink(name, fs::read_link(path)?.to_string_lossy(), options)?;
+        }
+        else {
+            zip.start_file(name, options)?;
+            io::copy(&mut fs::File::open(path)?, &mut zip)?;
+        }
+        Ok(())
+    })?;
+    zip.finish()?;
+    Ok(())
+}
+
+#[cfg(feature = "archive")]
+fn add_tar(mut file: fs::File, exists: bool, entry: &Path, name: &str, progress: &Progress) -> io::Result<()> {
+    if exists {
+        // The empty blocks ending the archive get written over
+        let end: u64 = tar_end(&file)?;
+        file.set_len(end)?;
+        file.seek(SeekFrom::Start(end))?;
+    }
+    let mut builder = tar::Builder::new(file);
+    append_tar(&mut builder, entry, name, progress)?;
+    builder.finish()
+}
+
+#[cfg(feature = "archive")]
+fn append_tar<W: Write>(builder: &mut tar::Builder<W>, entry: &Path, name: &str, progress: &Progress) -> io::Result<()> {
+    builder.follow_symlinks(false);
+    walk(entry, name, progress, &mut |path, name, _| builder.append_path_with_name(path, name))
+}
+
+/// Finds where the last entry of a tar archive ends
+#[cfg(feature = "archive")]
+fn tar_end(file: &fs::File) -> io::Result<u64> {
+    let mut end: u64 = 0;
+    for entry in tar::Archive::new(file).entries()? {
+        let entry = entry?;
+        end = entry.raw_file_position() + entry.size().div_ceil(512) * 512;
+    }
+    Ok(end)
+}
+
+/// An entry of an archive, as listed before extracting it
+#[cfg(feature = "archive")]
+struct Entry {
+    name: String,
+    /// Whether it is a symbolic or a hard link
+    link: bool,
+}
+
+/// Lists the entries of an archive
+#[cfg(feature = "archive")]
+fn entries(archive: &Path, format: Format) -> io::Result<Vec<Entry>> {
+    if format == Format::Zip {
+        let mut zip: ZipArchive<fs::File> = ZipArchive::new(fs::File::open(archive)?)?;
+        return (0..zip.len()).map(|i| -> io::Result<Entry> {
+            let file = zip.by_index(i)?;
+            Ok(Entry { name: file.name()?.to_string(), link: file.is_symlink() })
+        }).collect();
+    }
+    open_tar(archive, format)?.entries()?.map(|entry| -> io::Result<Entry> {
+        let entry = entry?;
+        let kind: tar::EntryType = entry.header().entry_type();
+        Ok(Entry { name: String::from_utf8_lossy(&entry.path_bytes()).to_string(), link: kind.is_symlink() || kind.is_hard_link() })
+    }).collect()
+}
+
+/// Opens a tar archive, decompressing it as it is read if needed
+#[cfg(feature = "archive")]
+fn open_tar(archive: &Path, format: Format) -> io::Result<tar::Archive<Box<dyn Read>>> {
+    let file: fs::File = fs::File::open(archive)?;
+    Ok(tar::Archive::new(if format == Format::TarGz { Box::new(GzDecoder::new(file)) } else { Box::new(file) }))
+}
+
+/// Returns whether extracting an entry named `name` stays inside the destination,
+/// absolute names and names going up with `..` don't
+#[cfg(feature = "archive")]
+fn is_contained(name: &str) -> bool {
+    let name: String = name.replace('\\', "/");
+    Path::new(&name).components().all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+}
+
+/// Picks where to extract an archive, a folder named after it next to it that doesn't exist yet
+pub fn extract_dir(archive: &Path, format: Format) -> PathBuf {
+    let name: String = archive.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+    let stem: &str = format.stem(&name);
+    (1..).map(|n: u32| archive.with_file_name(if n == 1 { stem.to_string() } else { format!("{} {}", stem, n) }))
+        .find(|dir| fs::symlink_metadata(dir).is_err())
+        .unwrap()
+}
+
+/// Extracts an archive into `dest`, which gets created, each entry extracted is counted in `progress`
+///
+/// Archives are refused whole when an entry would land outside of `dest` (zip-slip),
+/// and when they contain links, whatever files those point to could be written to through them
+#[cfg(feature = "archive")]
+pub fn extract(archive: &Path, dest: &Path, format: Format, progress: &Progress) -> io::Result<()> {
+    let entries: Vec<Entry> = entries(archive, format)?;
+    let dest_name = dest.file_name().unwrap_or_default().to_string_lossy();
+    if let Some(entry) = entries.iter().find(|entry| !is_contained(&entry.name)) {
+        return Err(outside(&entry.name, &dest_name));
+    }
+    let links: usize = entries.iter().filter(|entry| entry.link).count();
+    if links > 0 {
+        let message: String = format!("it has {} {} that could point outside of {}", links, if links == 1 { "link" } else { "links" }, dest_name);
+        return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+    }
+    progress.set_total(entries.len());
+    fs::create_dir(dest)?;
+    let result: io::Result<()> = match format {
+        Format::Zip => extract_zip(archive, dest, progress),
+        Format::Tar | Format::TarGz => extract_tar(archive, dest, format, progress),
+    };
+    // What a failed extraction left is incomplete
+    if result.is_err() {
+        let _ = fs::remove_dir_all(dest);
+    }
+    result
+}
+
+#[cfg(not(feature = "archive"))]
+pub fn extract(_archive: &Path, _dest: &Path, _format: Format, _progress: &Progress) -> io::Result<()> {
+    Err(unsupported())
+}
+
+#[cfg(feature = "archive")]
+fn extract_zip(archive: &Path, dest: &Path, progress: &Progress) -> io::Result<()> {
+    let mut zip: ZipArchive<fs::File> = ZipArchive::new(fs::File::open(archive)?)?;
+    for i in 0..zip.len() {
+        if progress.is_cancelled() {
+            return Err(cancelled());
+        }
+        let mut file = zip.by_index(i)?;
+        let Some(name) = file.enclosed_name() else {
+            return Err(outside(&file.name()?, &dest.file_name().unwrap_or_default().to_string_lossy()));
+        };
+        let target: PathBuf = dest.join(name);
+        if file.is_dir() {
+            fs::create_dir_all(&target)?;
+        }
+        else {
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            io::copy(&mut file, &mut fs::File::create(&target)?)?;
+            // Folders keep the default permissions, so that what they contain can be written
+            #[cfg(unix)]
+            if let Some(mode) = file.unix_mode() {
+                use std::os::unix::fs::PermissionsExt;
+                fs::set_permissions(&target, fs::Permissions::from_mode(mode & 0o777))?;
+            }
+        }
+        progress.step();
+    }
+    Ok(())
+}
+
+#[cfg(feature = "archive")]
+fn extract_tar(archive: &Path, dest: &Path, format: Format, progress: &Progress) -> io::Result<()> {
+    let mut tar = open_tar(archive, format)?;
+    for entry in tar.entries()? {
+        if progress.is_cancelled() {
+            return Err(cancelled());
+        }
+        let mut entry = entry?;
+        if !entry.unpack_in(dest)? {
+            return Err(outside(&String::from_utf8_lossy(&entry.path_bytes()), &dest.file_name().unwrap_or_default().to_string_lossy()));
+        }
+        progress.step();
+    }
+    Ok(())
+}
+
+#[cfg(feature = "archive")]
+fn outside(name: &str, dest_name: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("{} would end up outside of {}", name, dest_name))
+}
+
+#[cfg(feature = "archive")]
+fn cancelled() -> io::Error {
+    io::Error::new(io::ErrorKind::Interrupted, "cancelled")
+}
+
+#[cfg(not(feature = "archive"))]
+fn unsupported() -> io::Error {
+    io::Error::new(io::ErrorKind::Unsupported, "filez was built without the archive feature")
+}
+
+#[cfg(all(test, feature = "archive"))]
+mod tests {
+    use super::*;
+
+    /// A folder to archive, `dir/a.txt` and `dir/sub/b.txt`, in an empty scratch folder
+    fn scratch_dir(name: &str) -> PathBuf {
+        let root: PathBuf = std::env::temp_dir().join(format!("filez-archive-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("dir/sub")).unwrap();
+        fs::write(root.join("dir/a.txt"), "a").unwrap();
+        fs::write(root.join("dir/sub/b.txt"), "b").unwrap();
+        root
+    }
+
+    #[test]
+    fn names_going_outside_are_not_contained() {
+        assert!(is_contained("a/b.txt"));
+        assert!(is_contained("./a/"));
+        assert!(!is_contained("../a"));
+        assert!(!is_contained("a/../../b"));
+        assert!(!is_contained("/etc/passwd"));
+        assert!(!is_contained("..\\a"));
+    }
+
+    #[test]
+    fn archives_are_extracted_as_they_were_made() {
+        for (format, name) in [(Format::Zip, "dir.zip"), (Format::Tar, "dir.tar"), (Format::TarGz, "dir.tar.gz")] {
+            let root: PathBuf = scratch_dir(name);
+            let progress: Progress = Progress::default();
+            add(&root.join(name), &root.join("dir"), format, &progress).unwrap();
+            assert!(Format::of_file(&root.join(name)) == Some(format));
+            let dest: PathBuf = extract_dir(&root.join(name), format);
+            assert_eq!(dest, root.join("dir 2"));
+            extract(&root.join(name), &dest, format, &progress).unwrap();
+            assert_eq!(fs::read_to_string(dest.join("dir/a.txt")).unwrap(), "a");
+            assert_eq!(fs::read_to_string(dest.join("dir/sub/b.txt")).unwrap(), "b");
+            let _ = fs::remove_dir_all(&root);
+        }
+    }
+
+    #[test]
+    fn entries_are_added_to_existing_archives() {
+        for (format, name) in [(Format::Zip, "more.zip"), (Format::Tar, "more.tar")] {
+            let root: PathBuf = scratch_dir(name);
+            let progress: Progress = Progress::default();
+            add(&root.join(name), &root.join("dir/a.txt"), format, &progress).unwrap();
+            add(&root.join(name), &root.join("dir/sub"), format, &progress).unwrap();
+            // Zip archives end the names of folders with a slash, tar archives don't have to
+            let names: Vec<String> = entries(&root.join(name), format).unwrap().into_iter()
+                .map(|entry| entry.name.trim_end_matches('/').to_string())
+                .collect();
+            assert_eq!(names, ["a.txt", "sub", "sub/b.txt"]);
+            let _ = fs::remove_dir_all(&root);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn archives_with_links_are_not_extracted() {
+        for (format, name) in [(Format::Zip, "links.zip"), (Format::TarGz, "links.tar.gz")] {
+            let root: PathBuf = scratch_dir(name);
+            std::os::unix::fs::symlink("/etc", root.join("dir/etc")).unwrap();
+            let progress: Progress = Progress::default();
+            add(&root.join(name), &root.join("dir"), format, &progress).unwrap();
+            let err: io::Error = extract(&root.join(name), &root.join("out"), format, &progress).unwrap_err();
+            assert_eq!(err.to_string(), "it has 1 link that could point outside of out");
+            assert!(!root.join("out").exists());
+            let _ = fs::remove_dir_all(&root);
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use std::{
+    env,
+    io::{self, Write},
+    path::{Component, Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+/// Puts `text` into the system clipboard, using whichever clipboard tool the platform provides
+pub fn copy(text: &str) -> io::Result<()> {
+    let (program, args): (&str, &[&str]) = if cfg!(windows) {
+        ("clip", &[])
+    }
+    else if cfg!(target_os = "macos") {
+        ("pbcopy", &[])
+    }
+    else if env::var_os("WAYLAND_DISPLAY").is_some() {
+        ("wl-copy", &[])
+    }
+    else {
+        ("xclip", &["-selection", "clipboard"])
+    };
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    child.stdin.take().unwrap().write_all(text.as_bytes())?;
+    let status = child.wait()?;
+    if status.success() {
+        Ok(())
+    }
+    else {
+        Err(io::Error::other(format!("{} exited with {}", program, status)))
+    }
+}
+
+/// Quotes `path` so that it can be pasted as a single argument in a shell
+pub fn shell_escape(path: &str) -> String {
+    quote(path, cfg!(windows))
+}
+
+/// Quotes `path` for a POSIX shell, or for `cmd` with `cmd`
+fn quote(path: &str, cmd: bool) -> String {
+    // cmd expands `%VAR%`, even between quotes
+    let safe: &str = if cmd { "_-./:@+=," } else { "_-./:@%+=," };
+    if !path.is_empty() && path.chars().all(|c| c.is_ascii_alphanumeric() || safe.contains(c)) {
+        return path.to_string();
+    }
+    if cmd {
+        // Double quotes can't appear in Windows paths, `%` is escaped outside of them
+        format!("\"{}\"", path.replace('%', "\"^%\""))
+    }
+    else {
+        format!("'{}'", path.replace('\'', "'\\''"))
+    }
+}
+
+/// Writes `path` relative to the folder `base`, going up with `..` where they part,
+/// both are expected to be absolute and normalized
+///
+/// Paths that share no folder with `base`, only the root or not even the drive on Windows, are kept absolute
+/// rather than climbing up to the root with `..`
+pub fn relative_to(path: &Path, base: &Path) -> String {
+    let (path_components, base_components): (Vec<Component>, Vec<Component>) = (path.components().collect(), base.components().collect());
+    let common: usize = path_components.iter().zip(&base_components).take_while(|(a, b)| a == b).count();
+    if !path_components[..common].iter().any(|component| matches!(component, Component::Normal(_))) {
+        return path.to_string_lossy().to_string();
+    }
+    let mut relative: PathBuf = PathBuf::new();
+    for _ in common..base_components.len() {
+        relative.push("..");
+    }
+    for component in &path_components[common..] {
+        relative.push(component);
+    }
+    if relative.as_os_str().is_empty() {
+        ".".to_string()
+    }
+    else {
+        relative.to_string_lossy().to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_paths_are_left_alone() {
+        assert_eq!(quote("/tmp/notes.txt", false), "/tmp/notes.txt");
+        assert_eq!(quote("a-b_c+d=e,f@g:h", false), "a-b_c+d=e,f@g:h");
+        assert_eq!(quote("C:/tmp/notes.txt", true), "C:/tmp/notes.txt");
+    }
+
+    #[test]
+    fn posix_quoting() {
+        assert_eq!(quote("my file.txt", false), "'my file.txt'");
+        assert_eq!(quote("it's", false), "'it'\\''s'");
+        assert_eq!(quote("$HOME", false), "'$HOME'");
+        assert_eq!(quote("\"quoted\"", false), "'\"quoted\"'");
+        assert_eq!(quote("", false), "''");
+        assert_eq!(quote("100%", false), "100%");
+    }
+
+    #[test]
+    fn cmd_quoting() {
+        assert_eq!(quote("my file.txt", true), "\"my file.txt\"");
+        assert_eq!(quote("$HOME", true), "\"$HOME\"");
+        // `%` is outside of the quotes, escaped with `^`
+        assert_eq!(quote("%PATH%", true), "\"\"^%\"PATH\"^%\"\"");
+        assert_eq!(quote("100% done", true), "\"100\"^%\" done\"");
+    }
+
+    #[test]
+    fn relative_paths() {
+        let relative = |path: &str, base: &str| relative_to(Path::new(path), Path::new(base));
+        assert_eq!(relative("/home/me/src/main.rs", "/home/me"), "src/main.rs");
+        assert_eq!(relative("/home/me", "/home/me/src/deep"), "../..");
+        assert_eq!(relative("/home/me/docs/a.md", "/home/me/src"), "../docs/a.md");
+        assert_eq!(relative("/home/me", "/home/me"), ".");
+    }
+
+    #[test]
+    fn unrelated_bases_keep_absolute_paths() {
+        assert_eq!(relative_to(Path::new("/etc/hosts"), Path::new("/home/me/src")), "/etc/hosts");
+        assert_eq!(relative_to(Path::new("/"), Path::new("/home")), "/");
+    }
+}
@@ -0,0 +1,541 @@
+use std::{
+    collections::HashMap,
+    env,
+    fs,
+    io,
+    path::PathBuf,
+    str::FromStr,
+};
+
+use crate::COLOR_ROLES;
+use crate::format::{Column, DateFormat, Highlight};
+use crate::listing::ListFormat;
+use crate::sort::{Dotfiles, GroupOrder, SortMode};
+
+/// The config file written for users to fill in, with the defaults of every setting
+const TEMPLATE: &str = "\
+# filez settings, command line options take precedence over them
+# Uncomment a line to change a setting
+
+# input_budget = 32
+# wrap_cursor = false
+# enter_empty = true
+# enter_previews = false
+# confirm_open = 0
+# confirm_quit = false
+# search_enter = false
+# resolve_start = true
+# max_entries = 50000
+# rows = 0
+# refresh_ms = 100
+# sort = \"natural\"
+# sort_per_dir = false
+# read_only = false
+# color = true
+# mouse = true
+# ls_colors = true
+# icons = true
+# slashes = false
+# owner = false
+# git_status = false
+# link_targets = true
+# hidden = true
+# dotfiles = \"mixed\"
+# group_order = \"dirs-first\"
+# ignore = []
+# grid = false
+# tree = false
+# tree_depth = 1
+# exact_sizes = false
+# size_bars = false
+# dir_sizes = false
+# age_colors = false
+# extensions = false
+# snippets = false
+# tilde = false
+# highlight = \"reverse\"
+# date_format = \"full\"
+# columns = [\"git-status\", \"icon\", \"name\", \"extension\", \"date\", \"size\", \"owner\"]
+# newest = false
+# opener = \"xdg-open\"
+
+# Colors of parts of the interface, by name or number (256 colors), `on` gives the background
+# [colors]
+# dir = \"blue\"
+# selection = \"black on cyan\"
+
+# Icons by extension, before the built-in ones
+# [icons]
+# md = \"\u{f48a}\"
+
+# Openers by extension, `{}` is replaced with the path of the file
+# [open.md]
+# command = \"glow -p\"
+# detach = false
+";
+
+/// The names of the first colors, by number
+const COLOR_NAMES: &[&str] = &["black", "red", "green", "yellow", "blue", "magenta", "cyan", "white"];
+
+/// Rescanning more often than this would keep the disk busy for nothing
+const MIN_REFRESH_MS: u64 = 10;
+
+/// A command used to open files
+#[derive(Clone)]
+pub struct Opener {
+    /// The command line, `{}` is replaced with the path of the file (which is appended otherwise)
+    pub command: String,
+    /// Whether the command runs on its own (GUI apps) rather than in the terminal (TUI apps)
+    pub detach: bool,
+}
+
+/// Runtime settings of the explorer
+pub struct Config {
+    /// The directory to start browsing in
+    pub path: Option<String>,
+    /// Whether a start directory reached through symbolic links is shown as the directory they lead to
+    pub resolve_start: bool,
+    /// The maximum amount of pending inputs handled in a single frame
+    pub input_budget: usize,
+    /// Whether the cursor goes to the other end of the listing when moved past an end
+    pub wrap_cursor: bool,
+    /// Whether empty directories can be entered
+    pub enter_empty: bool,
+    /// The maximum amount of entries listed in a directory
+    pub max_entries: usize,
+    /// How many rows the listing takes at most, `0` fills the terminal
+    pub rows: usize,
+    /// How often the listing is read again, in milliseconds
+    pub refresh_ms: u64,
+    /// The order of the listing
+    pub sort: SortMode,
+    /// Whether each directory keeps the sort mode chosen in it
+    pub sort_per_dir: bool,
+    /// Whether actions that change files are disabled
+    pub read_only: bool,
+    /// Whether Enter toggles the preview of files instead of opening them
+    pub enter_previews: bool,
+    /// The size above which opening a file is confirmed first, in bytes, 0 to never ask
+    pub confirm_open: u64,
+    /// Whether quitting asks first while entries are marked
+    pub confirm_quit: bool,
+    /// Whether a search enters the directory it narrowed down to
+    pub search_enter: bool,
+    /// Whether colors are used, when the terminal supports them
+    pub color: bool,
+    /// Whether filez takes the mouse, otherwise the terminal keeps it for selecting text
+    pub mouse: bool,
+    /// Whether the owner column is shown (Unix)
+    pub owner: bool,
+    /// Whether the git status of entries is shown in front of them, inside repositories
+    pub git_status: bool,
+    /// Whether the targets of symbolic links are shown next to them
+    pub link_targets: bool,
+    /// Whether hidden entries (dotfiles) are listed
+    pub hidden: bool,
+    /// Where hidden entries go among the others
+    pub dotfiles: Dotfiles,
+    /// Whether directories come before files, after them or among them
+    pub group_order: GroupOrder,
+    /// Globs of the names that are never listed, unless the ignore list is turned off
+    pub ignore: Vec<String>,
+    /// Whether the listing starts as a grid of names rather than a list with details
+    pub grid: bool,
+    /// Whether the listing starts as a tree
+    pub tree: bool,
+    /// How many levels of directories the tree view expands by itself
+    pub tree_depth: usize,
+    /// Whether file names are colored according to `$LS_COLORS` when it is set
+    pub ls_colors: bool,
+    /// Whether the icon column is shown
+    pub icons: bool,
+    /// Whether directory names end with a separator, like `ls -F` does
+    pub slashes: bool,
+    /// Whether the first line of small text files is shown after their row
+    pub snippets: bool,
+    /// Whether the extensions of files are shown in their own column
+    pub extensions: bool,
+    /// Whether the home directory is shown as `~` in the header
+    pub tilde: bool,
+    /// How the selected row stands out
+    pub highlight: Highlight,
+    /// How the date column writes dates
+    pub date_format: DateFormat,
+    /// Which columns the rows have and in what order
+    pub columns: Vec<Column>,
+    /// Whether the footer names the most recently modified entry
+    pub newest: bool,
+    /// Whether sizes are shown in bytes rather than with units
+    pub exact_sizes: bool,
+    /// Whether sizes are drawn as bars, as long as the largest one allows
+    pub size_bars: bool,
+    /// Whether directories show the total size of their contents rather than a dash
+    pub dir_sizes: bool,
+    /// Whether the date column is colored from bright to dim by how recent the dates are
+    pub age_colors: bool,
+    /// Where to write the last browsed directory when quitting with `Q`
+    pub cd_file: Option<String>,
+    /// The directory whose tree is indexed for jumping anywhere, the home directory if not set
+    pub jump_root: Option<String>,
+    /// Whether the listing is printed to stdout instead of browsed
+    pub list: bool,
+    /// How the listing is printed with `list`
+    pub list_format: ListFormat,
+    /// The command used to open files that don't have a specific opener
+    pub opener: Opener,
+    /// Specific openers, by file extension
+    pub openers: HashMap<String, Opener>,
+    /// Colors of parts of the interface, by name, as a foreground and maybe a background
+    pub colors: HashMap<String, (i16, Option<i16>)>,
+    /// Icons of files, by extension, before the built-in ones
+    pub extension_icons: HashMap<String, String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            path: None,
+            resolve_start: true,
+            input_budget: 32,
+            wrap_cursor: false,
+            enter_empty: true,
+            max_entries: 50000,
+            rows: 0,
+            refresh_ms: 100,
+            sort: SortMode::Natural,
+            sort_per_dir: false,
+            read_only: false,
+            enter_previews: false,
+            confirm_open: 0,
+            confirm_quit: false,
+            search_enter: false,
+            // https://no-color.org
+            color: env::var_os("NO_COLOR").is_none_or(|value| value.is_empty()),
+            mouse: true,
+            owner: false,
+            git_status: false,
+            link_targets: true,
+            hidden: true,
+            dotfiles: Dotfiles::Mixed,
+            group_order: GroupOrder::DirsFirst,
+            ignore: vec![],
+            grid: false,
+            tree: false,
+            tree_depth: 1,
+            ls_colors: true,
+            icons: true,
+            slashes: false,
+            snippets: false,
+            extensions: false,
+            tilde: false,
+            highlight: Highlight::Reverse,
+            columns: Column::DEFAULT.to_vec(),
+            date_format: DateFormat::Full,
+            newest: false,
+            exact_sizes: false,
+            size_bars: false,
+            dir_sizes: false,
+            age_colors: false,
+            cd_file: None,
+            jump_root: None,
+            list: false,
+            list_format: ListFormat::Plain,
+            opener: Opener {
+                command: default_opener().to_string(),
+                detach: true,
+            },
+            openers: HashMap::new(),
+            colors: HashMap::new(),
+            extension_icons: HashMap::new(),
+        }
+    }
+}
+
+impl Config {
+
+    /// Builds the configuration from the config file, then the command line arguments (without the program name)
+    pub fn load(args: impl Iterator<Item = String>) -> Result<Config, String> {
+        let mut config: Config = Config::default();
+        if let Some(file) = Config::file_path() {
+            if let Ok(text) = fs::read_to_string(&file) {
+                config.apply_file(&text).map_err(|err| format!("{}: {}", file.display(), err))?;
+            }
+        }
+        config.apply_args(args)?;
+        Ok(config)
+    }
+
+    /// Returns the location of the config file
+    pub fn file_path() -> Option<PathBuf> {
+        let dir: PathBuf = if cfg!(windows) {
+            PathBuf::from(env::var_os("APPDATA")?)
+        }
+        else if let Some(dir) = env::var_os("XDG_CONFIG_HOME") {
+            PathBuf::from(dir)
+        }
+        else {
+            PathBuf::from(env::var_os("HOME")?).join(".config")
+        };
+        Some(dir.join("filez").join("config.toml"))
+    }
+
+    /// Writes the config file with every setting commented out if it doesn't exist yet, returns its path
+    pub fn create_file() -> io::Result<PathBuf> {
+        let file: PathBuf = Config::file_path().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no config folder"))?;
+        if !file.exists() {
+            if let Some(dir) = file.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            fs::write(&file, TEMPLATE)?;
+        }
+        Ok(file)
+    }
+
+    /// Returns the opener to use for a file
+    pub fn opener_for(&self, file_name: &str) -> &Opener {
+        file_name.rsplit_once('.')
+            .and_then(|(_, ext)| self.openers.get(&ext.to_lowercase()))
+            .unwrap_or(&self.opener)
+    }
+
+    /// Applies the command line arguments
+    fn apply_args(&mut self, mut args: impl Iterator<Item = String>) -> Result<(), String> {
+        while let Some(arg) = args.next() {
+            if let Some(flag) = arg.strip_prefix("--") {
+                let (name, inline) = match flag.split_once('=') {
+                    Some((name, value)) => (name.to_string(), Some(value.to_string())),
+                    None => (flag.to_string(), None),
+                };
+                let mut value = || inline.clone().or_else(|| args.next()).ok_or("missing value".to_string());
+                match name.as_str() {
+                    "input-budget" => self.set("input_budget", &value()?),
+                    "wrap-cursor" => self.set("wrap_cursor", "true"),
+                    "no-enter-empty" => self.set("enter_empty", "false"),
+                    "enter-previews" => self.set("enter_previews", "true"),
+                    "confirm-open" => self.set("confirm_open", &value()?),
+                    "confirm-quit" => self.set("confirm_quit", "true"),
+                    "search-enter" => self.set("search_enter", "true"),
+                    "keep-links" => self.set("resolve_start", "false"),
+                    "opener" => self.set("opener", &value()?),
+                    "max-entries" => self.set("max_entries", &value()?),
+                    "rows" => self.set("rows", &value()?),
+                    "refresh-ms" => self.set("refresh_ms", &value()?),
+                    "sort" => self.set("sort", &value()?),
+                    "sort-per-dir" => self.set("sort_per_dir", "true"),
+                    "read-only" => self.set("read_only", "true"),
+                    "no-icons" => self.set("icons", "false"),
+                    "slashes" => self.set("slashes", "true"),
+                    "snippets" => self.set("snippets", "true"),
+                    "extensions" => self.set("extensions", "true"),
+                    "tilde" => self.set("tilde", "true"),
+                    "highlight" => self.set("highlight", &value()?),
+                    "date-format" => self.set("date_format", &value()?),
+                    "columns" => self.set("columns", &value()?),
+                    "newest" => self.set("newest", "true"),
+                    "no-hidden" => self.set("hidden", "false"),
+                    "dotfiles" => self.set("dotfiles", &value()?),
+                    "group-order" => self.set("group_order", &value()?),
+                    "tree" => self.set("tree", "true"),
+                    "depth" => self.set("tree_depth", &value()?).and_then(|_| self.set("tree", "true")),
+                    "owner" => self.set("owner", "true"),
+                    "git-status" => self.set("git_status", "true"),
+                    "no-link-targets" => self.set("link_targets", "false"),
+                    "no-color" => self.set("color", "false"),
+                    "no-mouse" => self.set("mouse", "false"),
+                    "no-ls-colors" => self.set("ls_colors", "false"),
+                    "exact-sizes" => self.set("exact_sizes", "true"),
+                    "size-bars" => self.set("size_bars", "true"),
+                    "dir-sizes" => self.set("dir_sizes", "true"),
+                    "grid" => self.set("grid", "true"),
+                    "ignore" => self.set("ignore", &value()?),
+                    "age-colors" => self.set("age_colors", "true"),
+                    "cd-file" => value().map(|file| { self.cd_file = Some(file); }),
+                    "jump-root" => self.set("jump_root", &value()?),
+                    "list" => { self.list = true; Ok(()) }
+                    "format" | "output-format" => value().and_then(|format| parse_value(&format)).map(|format| { self.list_format = format; }),
+                    _ => Err("unknown option".to_string()),
+                }.map_err(|err| format!("--{}: {}", name, err))?;
+            }
+            else if self.path.is_none() {
+                self.path = Some(arg);
+            }
+            else {
+                return Err(format!("unexpected argument '{}'", arg));
+            }
+        }
+        Ok(())
+    }
+
+    /// Applies the contents of a config file
+    ///
+    /// The file is a small subset of TOML: `key = value` lines grouped under `[section]` headers,
+    /// the openers of an extension are defined in `[open.<ext>]` sections, colors in `[colors]` and icons in `[icons]`
+    fn apply_file(&mut self, text: &str) -> Result<(), String> {
+        let mut section: String = String::new();
+        for (i, line) in text.lines().enumerate() {
+            let line: &str = line.trim();
+            if line.is_empty() || line.starts_with('#') { continue }
+            let err = |err: String| format!("line {}: {}", i+1, err);
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                section = name.trim().to_string();
+                continue;
+            }
+            let (key, value) = line.split_once('=').ok_or_else(|| err("expected `key = value`".to_string()))?;
+            let (key, value) = (key.trim(), unquote(value.trim()));
+            if section.is_empty() {
+                self.set(key, value).map_err(err)?;
+            }
+            else if let Some(ext) = section.strip_prefix("open.") {
+                let opener: &mut Opener = self.openers.entry(ext.to_lowercase()).or_insert(Opener { command: String::new(), detach: false });
+                match key {
+                    "command" => opener.command = value.to_string(),
+                    "detach" => opener.detach = parse_value(value).map_err(err)?,
+                    _ => return Err(err(format!("unknown key '{}'", key))),
+                }
+            }
+            else if section == "colors" {
+                if !COLOR_ROLES.iter().any(|(name, ..)| *name == key) {
+                    return Err(err(format!("unknown color '{}'", key)));
+                }
+                self.colors.insert(key.to_string(), parse_colors(value).map_err(err)?);
+            }
+            else if section == "icons" {
+                self.extension_icons.insert(key.trim_start_matches('.').to_lowercase(), value.to_string());
+            }
+            else {
+                return Err(err(format!("unknown section [{}]", section)));
+            }
+        }
+        Ok(())
+    }
+
+    /// Sets a top-level setting from its textual value
+    fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "input_budget" => self.input_budget = parse_value::<usize>(value)?.max(1),
+            "wrap_cursor" => self.wrap_cursor = parse_value(value)?,
+            "enter_empty" => self.enter_empty = parse_value(value)?,
+            "enter_previews" => self.enter_previews = parse_value(value)?,
+            "confirm_open" => self.confirm_open = parse_size(value)?,
+            "confirm_quit" => self.confirm_quit = parse_value(value)?,
+            "search_enter" => self.search_enter = parse_value(value)?,
+            "opener" => self.opener.command = value.to_string(),
+            "jump_root" => self.jump_root = Some(value.to_string()),
+            "max_entries" => self.max_entries = parse_value::<usize>(value)?.max(1),
+            "rows" => self.rows = parse_value(value)?,
+            "refresh_ms" => {
+                self.refresh_ms = parse_value(value)?;
+                if self.refresh_ms < MIN_REFRESH_MS {
+                    return Err(format!("the refresh interval can't be shorter than {}ms", MIN_REFRESH_MS));
+                }
+            }
+            "sort" => self.sort = parse_value(value)?,
+            "sort_per_dir" => self.sort_per_dir = parse_value(value)?,
+            "read_only" => self.read_only = parse_value(value)?,
+            "color" => self.color = parse_value(value)?,
+            "mouse" => self.mouse = parse_value(value)?,
+            "ls_colors" => self.ls_colors = parse_value(value)?,
+            "icons" => self.icons = parse_value(value)?,
+            "slashes" => self.slashes = parse_value(value)?,
+            "resolve_start" => self.resolve_start = parse_value(value)?,
+            "snippets" => self.snippets = parse_value(value)?,
+            "extensions" => self.extensions = parse_value(value)?,
+            "tilde" => self.tilde = parse_value(value)?,
+            "highlight" => self.highlight = parse_value(value)?,
+            "date_format" => self.date_format = parse_value(value)?,
+            "columns" => self.columns = Column::with_name(parse_list(value).iter().map(|column| parse_value(column)).collect::<Result<_, _>>()?),
+            "newest" => self.newest = parse_value(value)?,
+            "owner" => self.owner = parse_value(value)?,
+            "git_status" => self.git_status = parse_value(value)?,
+            "link_targets" => self.link_targets = parse_value(value)?,
+            "hidden" => self.hidden = parse_value(value)?,
+            "dotfiles" => self.dotfiles = parse_value(value)?,
+            "group_order" => self.group_order = parse_value(value)?,
+            "grid" => self.grid = parse_value(value)?,
+            "ignore" => self.ignore = parse_list(value),
+            "tree" => self.tree = parse_value(value)?,
+            "tree_depth" => self.tree_depth = parse_value(value)?,
+            "exact_sizes" => self.exact_sizes = parse_value(value)?,
+            "size_bars" => self.size_bars = parse_value(value)?,
+            "dir_sizes" => self.dir_sizes = parse_value(value)?,
+            "age_colors" => self.age_colors = parse_value(value)?,
+            _ => return Err(format!("unknown setting '{}'", key)),
+        }
+        Ok(())
+    }
+
+}
+
+/// Returns the command that opens files with the default application of the system
+fn default_opener() -> &'static str {
+    if cfg!(windows) { "explorer" }
+    else if cfg!(target_os = "macos") { "open" }
+    else { "xdg-open" }
+}
+
+/// Strips the quotes around a string value, if any
+fn unquote(value: &str) -> &str {
+    value.strip_prefix('"').and_then(|v| v.strip_suffix('"')).unwrap_or(value)
+}
+
+/// Parses a list of strings, `["a", "b"]` as in TOML or `a,b` as on the command line
+fn parse_list(value: &str) -> Vec<String> {
+    let value: &str = value.trim();
+    let value: &str = value.strip_prefix('[').and_then(|v| v.strip_suffix(']')).unwrap_or(value);
+    value.split(',').map(|item| unquote(item.trim()).to_string()).filter(|item| !item.is_empty()).collect()
+}
+
+/// Parses a size in bytes, which can end with a binary unit (`512K`, `1.5G`...)
+fn parse_size(value: &str) -> Result<u64, String> {
+    let units: &[(char, u64)] = &[('K', 1 << 10), ('M', 1 << 20), ('G', 1 << 30), ('T', 1 << 40)];
+    let upper: String = value.trim().to_uppercase();
+    let number: &str = upper.strip_suffix("IB").or_else(|| upper.strip_suffix('B')).unwrap_or(&upper);
+    let (number, unit): (&str, u64) = match units.iter().find(|(suffix, _)| number.ends_with(*suffix)) {
+        Some((_, unit)) => (number[..number.len()-1].trim_end(), *unit),
+        None => (number, 1),
+    };
+    let size: f64 = number.parse().map_err(|_| format!("invalid size '{}'", value))?;
+    if size < 0.0 {
+        return Err(format!("invalid size '{}'", value));
+    }
+    Ok((size * unit as f64) as u64)
+}
+
+/// Parses colors like `blue` or `black on cyan`, the background is left as it is without `on`
+fn parse_colors(value: &str) -> Result<(i16, Option<i16>), String> {
+    let (fg, bg): (&str, Option<&str>) = match value.split_once(" on ") {
+        Some((fg, bg)) => (fg, Some(bg)),
+        None => (value, None),
+    };
+    Ok((parse_color(fg)?, bg.map(parse_color).transpose()?))
+}
+
+/// Parses a color by name or by number, up to 255 for terminals that have that many
+fn parse_color(value: &str) -> Result<i16, String> {
+    let name: String = value.trim().to_lowercase();
+    match COLOR_NAMES.iter().position(|color| *color == name) {
+        Some(color) => Ok(color as i16),
+        None => name.parse::<u8>().map(i16::from).map_err(|_| format!("invalid color '{}'", value.trim())),
+    }
+}
+
+/// Parses a setting value
+fn parse_value<T: FromStr>(value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("invalid value '{}'", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn colors_and_icons_are_read_from_their_sections() {
+        let mut config: Config = Config::default();
+        config.apply_file("[colors]\ndir = \"magenta\"\nselection = \"white on 52\"\n[icons]\n.MD = \"M\"\n").unwrap();
+        assert_eq!(config.colors["dir"], (5, None));
+        assert_eq!(config.colors["selection"], (7, Some(52)));
+        assert_eq!(config.extension_icons["md"], "M");
+        assert!(Config::default().apply_file("[colors]\nnothing = \"red\"\n").is_err());
+        assert!(Config::default().apply_file("[colors]\ndir = \"purple\"\n").is_err());
+    }
+}
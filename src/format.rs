@@ -0,0 +1,307 @@
+use chrono::{
+    DateTime, Local,
+};
+use std::{
+    fs::Metadata,
+    str::FromStr,
+    time::SystemTime,
+};
+
+/// Splits a file name into its base name and its extension (without the dot),
+/// dotfiles with no other dot have no extension
+pub fn split_extension(name: &str) -> (&str, &str) {
+    match name.rfind('.') {
+        Some(i) if i > 0 => (&name[..i], &name[i+1..]),
+        _ => (name, ""),
+    }
+}
+
+/// Formats a point in time in the local timezone
+pub fn format_time(time: SystemTime) -> String {
+    DateTime::<Local>::from(time).format("%d-%m-%Y %H:%M").to_string()
+}
+
+/// Formats a point in time in the local timezone in little space, only the time for today and only the day before
+pub fn format_short_time(time: SystemTime) -> String {
+    let time: DateTime<Local> = DateTime::from(time);
+    let format: &str = if time.date_naive() == Local::now().date_naive() { "%H:%M" } else { "%d-%m-%Y" };
+    time.format(format).to_string()
+}
+
+/// Formats how long ago `time` was, e.g. `5 min ago`, times in the future are `just now`
+pub fn format_age(time: SystemTime) -> String {
+    let secs: u64 = SystemTime::now().duration_since(time).map(|age| age.as_secs()).unwrap_or(0);
+    match secs {
+        0..60 => "just now".to_string(),
+        60..3600 => format!("{} min ago", secs / 60),
+        3600..86400 => format!("{} h ago", secs / 3600),
+        _ => format!("{} days ago", secs / 86400),
+    }
+}
+
+/// The timestamp shown next to the entries
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TimeColumn {
+    Accessed,
+    Modified,
+    Created,
+}
+
+impl TimeColumn {
+
+    /// Returns the column that comes after this one when cycling
+    pub fn next(self) -> TimeColumn {
+        match self {
+            TimeColumn::Accessed => TimeColumn::Modified,
+            TimeColumn::Modified => TimeColumn::Created,
+            TimeColumn::Created => TimeColumn::Accessed,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            TimeColumn::Accessed => "accessed",
+            TimeColumn::Modified => "modified",
+            TimeColumn::Created => "created",
+        }
+    }
+
+    /// Reads the timestamp of the column, not every platform records all of them
+    pub fn get(self, meta: &Metadata) -> Option<SystemTime> {
+        match self {
+            TimeColumn::Accessed => meta.accessed().ok(),
+            TimeColumn::Modified => meta.modified().ok(),
+            TimeColumn::Created => meta.created().ok(),
+        }
+    }
+
+}
+
+/// How the date column writes dates
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DateFormat {
+    /// The day and the time
+    Full,
+    /// The time for today, the day otherwise
+    Short,
+    /// Short dates when the full ones don't fit the terminal
+    Auto,
+}
+
+impl DateFormat {
+
+    /// Returns the format that comes after this one when cycling
+    pub fn next(self) -> DateFormat {
+        match self {
+            DateFormat::Full => DateFormat::Short,
+            DateFormat::Short => DateFormat::Auto,
+            DateFormat::Auto => DateFormat::Full,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            DateFormat::Full => "full",
+            DateFormat::Short => "short",
+            DateFormat::Auto => "auto",
+        }
+    }
+
+}
+
+impl FromStr for DateFormat {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "full" => Ok(DateFormat::Full),
+            "short" => Ok(DateFormat::Short),
+            "auto" => Ok(DateFormat::Auto),
+            _ => Err(()),
+        }
+    }
+}
+
+/// How the selected row stands out
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Highlight {
+    /// The name in reverse video
+    Reverse,
+    /// A colored bar across the whole row
+    Bar,
+    /// A `>` in front of the row
+    Gutter,
+}
+
+impl FromStr for Highlight {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "reverse" => Ok(Highlight::Reverse),
+            "bar" => Ok(Highlight::Bar),
+            "gutter" => Ok(Highlight::Gutter),
+            _ => Err(()),
+        }
+    }
+}
+
+/// A column of the rows of the listing
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Column {
+    /// The git status of entries, while it is shown and inside of a repository
+    GitStatus,
+    /// The icon, while icons are shown
+    Icon,
+    /// The name, cut by the column after it
+    Name,
+    /// The extension of files, while extensions are shown apart
+    Extension,
+    Date,
+    Size,
+    /// The owner, while it is shown
+    Owner,
+    Permissions,
+}
+
+impl Column {
+
+    /// The columns in the order they come in by default, every one but the permissions
+    pub const DEFAULT: &[Column] = &[Column::GitStatus, Column::Icon, Column::Name, Column::Extension, Column::Date, Column::Size, Column::Owner];
+
+    /// Completes a list of columns without the name, which is the one of the details after it,
+    /// with the git status, icon and name coming first like by default
+    pub fn with_name(mut columns: Vec<Column>) -> Vec<Column> {
+        if !columns.contains(&Column::Name) {
+            columns.splice(0..0, [Column::GitStatus, Column::Icon, Column::Name]);
+        }
+        columns
+    }
+
+}
+
+impl FromStr for Column {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "git-status" => Ok(Column::GitStatus),
+            "icon" => Ok(Column::Icon),
+            "name" => Ok(Column::Name),
+            "extension" => Ok(Column::Extension),
+            "date" => Ok(Column::Date),
+            "size" => Ok(Column::Size),
+            "owner" => Ok(Column::Owner),
+            "permissions" => Ok(Column::Permissions),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Formats the accessed, modified and created times of a file, those the platform doesn't record are `None`
+pub fn all_times(meta: &Metadata) -> (Option<String>, Option<String>, Option<String>) {
+    let [accessed, modified, created] = [TimeColumn::Accessed, TimeColumn::Modified, TimeColumn::Created]
+        .map(|column| column.get(meta).map(format_time));
+    (accessed, modified, created)
+}
+
+/// Formats a size in bytes with binary units, e.g. `1.5 KiB`
+pub fn human_size(bytes: u64) -> String {
+    const UNITS: &[&str] = &["B", "KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
+    let mut size: f64 = bytes as f64;
+    let mut unit: usize = 0;
+    while size >= 1024.0 && unit < UNITS.len()-1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 { format!("{} {}", bytes, UNITS[0]) } else { format!("{:.1} {}", size, UNITS[unit]) }
+}
+
+/// Formats a size in bytes exactly, with thousands separators, e.g. `1,048,576`
+pub fn grouped_bytes(bytes: u64) -> String {
+    let digits: String = bytes.to_string();
+    let mut text: String = String::with_capacity(digits.len() + digits.len()/3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) { text.push(','); }
+        text.push(c);
+    }
+    text
+}
+
+/// Draws `bytes` as a bar `width` columns long for `largest`, eighths of a column make small differences visible
+pub fn size_bar(bytes: u64, largest: u64, width: usize) -> String {
+    const PARTS: &[char] = &['\u{258f}', '\u{258e}', '\u{258d}', '\u{258c}', '\u{258b}', '\u{258a}', '\u{2589}'];
+    if largest == 0 || bytes == 0 { return String::new(); }
+    let eighths: usize = ((bytes as f64 / largest as f64) * (width * 8) as f64).round().max(1.0) as usize;
+    let mut bar: String = "\u{2588}".repeat(eighths / 8);
+    if let Some(&part) = (eighths % 8).checked_sub(1).and_then(|i| PARTS.get(i)) { bar.push(part); }
+    bar
+}
+
+/// Formats the permissions of a file, `rwxr-xr-x` style on Unix
+#[cfg(unix)]
+pub fn permissions(meta: &Metadata) -> String {
+    use std::os::unix::fs::PermissionsExt;
+    mode_string(meta.permissions().mode())
+}
+
+/// Formats the permission bits of a Unix mode, `rwxr-xr-x` style
+#[cfg(unix)]
+pub fn mode_string(mode: u32) -> String {
+    let mut text: String = String::with_capacity(9);
+    for shift in [6, 3, 0] {
+        let bits: u32 = (mode >> shift) & 0o7;
+        text.push(if bits & 0o4 != 0 { 'r' } else { '-' });
+        text.push(if bits & 0o2 != 0 { 'w' } else { '-' });
+        text.push(if bits & 0o1 != 0 { 'x' } else { '-' });
+    }
+    text
+}
+
+/// Formats the permissions of a file, whether it is read-only elsewhere
+#[cfg(not(unix))]
+pub fn permissions(meta: &Metadata) -> String {
+    if meta.permissions().readonly() { "read-only" } else { "read-write" }.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn human_sizes() {
+        assert_eq!(human_size(0), "0 B");
+        assert_eq!(human_size(1023), "1023 B");
+        assert_eq!(human_size(1024), "1.0 KiB");
+        assert_eq!(human_size(1536), "1.5 KiB");
+        assert_eq!(human_size(1 << 30), "1.0 GiB");
+        assert_eq!(human_size(u64::MAX), "16.0 EiB");
+    }
+
+    #[test]
+    fn grouped_sizes() {
+        assert_eq!(grouped_bytes(0), "0");
+        assert_eq!(grouped_bytes(999), "999");
+        assert_eq!(grouped_bytes(1000), "1,000");
+        assert_eq!(grouped_bytes(1048576), "1,048,576");
+        assert_eq!(grouped_bytes(123456789012), "123,456,789,012");
+    }
+
+    #[test]
+    fn size_bars() {
+        assert_eq!(size_bar(100, 100, 4), "\u{2588}".repeat(4));
+        assert_eq!(size_bar(50, 100, 4), "\u{2588}".repeat(2));
+        // Eighths of a column end the bar
+        assert_eq!(size_bar(1, 8, 1), "\u{258f}");
+        assert_eq!(size_bar(9, 16, 2), "\u{2588}\u{258f}");
+        // Tiny files still get a sliver, empty ones nothing
+        assert_eq!(size_bar(1, u64::MAX, 4), "\u{258f}");
+        assert_eq!(size_bar(0, 100, 4), "");
+        assert_eq!(size_bar(5, 0, 4), "");
+    }
+
+    #[test]
+    fn columns_without_the_name_are_details() {
+        let details: Vec<Column> = Column::with_name(vec![Column::Size, Column::Date]);
+        assert_eq!(details, [Column::GitStatus, Column::Icon, Column::Name, Column::Size, Column::Date]);
+        let layout: Vec<Column> = vec![Column::Name, Column::Icon, Column::Size];
+        assert_eq!(Column::with_name(layout.clone()), layout);
+    }
+}
@@ -0,0 +1,136 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    time::SystemTime,
+};
+
+/// Returns the root of the git repository `path` is in, the closest directory containing `.git`
+///
+/// `.git` is a file rather than a directory in worktrees and submodules
+pub fn repo_root(path: &Path) -> Option<PathBuf> {
+    path.ancestors()
+        .find(|ancestor| ancestor.join(".git").exists())
+        .map(Path::to_path_buf)
+}
+
+/// Returns the commits that touched `path`, one `hash subject` line each from the latest,
+/// the error says why there is no history
+pub fn log(path: &Path) -> Result<Vec<String>, String> {
+    let dir: &Path = path.parent().unwrap_or(path);
+    let name = path.file_name().unwrap_or(path.as_os_str());
+    if repo_root(dir).is_none() {
+        return Err("not in a git repository".to_string());
+    }
+    let git = |args: &[&str]| Command::new("git")
+        .args(args).arg("--").arg(name)
+        .current_dir(dir)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .map_err(|err| format!("could not run git: {}", err));
+    if !git(&["ls-files", "--error-unmatch"])?.status.success() {
+        return Err(format!("{} is not tracked by git", name.to_string_lossy()));
+    }
+    // Files are followed through their renames, which git can't do for directories
+    let output = git(if path.is_dir() { &["log", "--oneline"] } else { &["log", "--oneline", "--follow"] })?;
+    if !output.status.success() {
+        return Err("git log failed".to_string());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).lines().map(str::to_string).collect())
+}
+
+/// What the git status of a directory was computed for: the directory, the commit checked out
+/// and how many entries the listing had and when the last of them changed
+type StatusKey = (PathBuf, String, usize, Option<SystemTime>);
+
+/// The git status of the entries of the current directory, only asked to `git` again when something changed
+#[derive(Default)]
+pub struct StatusCache {
+    key: Option<StatusKey>,
+    /// Markers by path, directories get the most important marker of what they contain
+    marks: HashMap<PathBuf, String>,
+}
+
+impl StatusCache {
+
+    /// Returns whether the directory of the markers is inside a repository
+    pub fn in_repo(&self) -> bool {
+        self.key.is_some()
+    }
+
+    /// Returns the marker of `path` (`M`, `A`, `??`...), `None` if it is unchanged or not in a repository
+    pub fn mark(&self, path: &Path) -> Option<&str> {
+        self.marks.get(path).map(String::as_str)
+    }
+
+    /// Makes the markers match the directory `dir`, whose listing has `entries` entries modified at the latest at `newest`
+    pub fn update(&mut self, dir: &Path, entries: usize, newest: Option<SystemTime>) {
+        let Some(root) = repo_root(dir) else {
+            self.key = None;
+            self.marks.clear();
+            return;
+        };
+        let key: StatusKey = (dir.to_path_buf(), head(&root), entries, newest);
+        if self.key.as_ref() == Some(&key) {
+            return;
+        }
+        self.key = Some(key);
+        // Without git, there are no markers rather than an error each time
+        self.marks = status(dir, &root).unwrap_or_default();
+    }
+
+}
+
+/// Returns the commit checked out in the repository at `root`, as written in `.git`
+fn head(root: &Path) -> String {
+    let git: PathBuf = root.join(".git");
+    let head: String = fs::read_to_string(git.join("HEAD")).unwrap_or_default();
+    match head.trim().strip_prefix("ref: ") {
+        Some(reference) => fs::read_to_string(git.join(reference)).unwrap_or_else(|_| head.clone()),
+        None => head,
+    }
+}
+
+/// Runs `git status` for `dir`, the markers of changed entries are given to them and to their directories up to `dir`
+fn status(dir: &Path, root: &Path) -> Option<HashMap<PathBuf, String>> {
+    let output = Command::new("git")
+        .arg("status").arg("--porcelain").arg("-z").arg("--").arg(".")
+        .current_dir(dir)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let text: String = String::from_utf8_lossy(&output.stdout).to_string();
+    let mut marks: HashMap<PathBuf, String> = HashMap::new();
+    let mut records = text.split('\0').filter(|record| record.len() > 3);
+    while let Some(record) = records.next() {
+        let (xy, name) = record.split_at(3);
+        // Renamed and copied entries are followed by where they come from
+        if xy.contains(['R', 'C']) {
+            records.next();
+        }
+        let mark: &str = xy.trim();
+        let path: PathBuf = root.join(name.trim_end_matches('/'));
+        for ancestor in path.ancestors().take_while(|ancestor| ancestor.starts_with(dir) && *ancestor != dir) {
+            let current: Option<&String> = marks.get(ancestor);
+            if current.is_none_or(|current| rank(mark) < rank(current)) {
+                marks.insert(ancestor.to_path_buf(), mark.to_string());
+            }
+        }
+    }
+    Some(marks)
+}
+
+/// How much a marker matters when a directory contains entries in different states, lower is more
+fn rank(mark: &str) -> u8 {
+    if mark.contains('U') { 0 }
+    else if mark.contains('M') { 1 }
+    else if mark.contains('A') { 2 }
+    else if mark.contains('D') { 3 }
+    else if mark.contains('R') { 4 }
+    else { 5 }
+}
@@ -0,0 +1,364 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::Read,
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Sender},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+use crate::FileStat;
+
+/// Summary of the direct children of a directory
+pub struct DirInfo {
+    /// The amount of entries in the directory
+    pub children: usize,
+    /// The total size of the files directly in the directory
+    pub size: u64,
+    /// The name of the most recently modified entry
+    pub newest: Option<String>,
+}
+
+impl DirInfo {
+
+    /// Reads the directory at `path` to summarize its contents
+    pub fn compute(path: &str) -> Option<DirInfo> {
+        let mut info: DirInfo = DirInfo { children: 0, size: 0, newest: None };
+        let mut newest_time: Option<SystemTime> = None;
+        for entry in fs::read_dir(path).ok()?.flatten() {
+            info.children += 1;
+            let Ok(meta) = entry.metadata() else { continue };
+            if meta.is_file() {
+                info.size += meta.len();
+            }
+            if let Ok(modified) = meta.modified() {
+                if newest_time.is_none_or(|time| modified > time) {
+                    newest_time = Some(modified);
+                    info.newest = Some(entry.file_name().to_string_lossy().to_string());
+                }
+            }
+        }
+        Some(info)
+    }
+
+}
+
+/// Returns the total size of the files at or under `path`, symbolic links are not followed
+pub fn disk_usage(path: &Path) -> u64 {
+    match fs::symlink_metadata(path) {
+        Ok(meta) if meta.is_dir() => fs::read_dir(path)
+            .map(|entries| entries.flatten().map(|entry| disk_usage(&entry.path())).sum())
+            .unwrap_or(0),
+        Ok(meta) => meta.len(),
+        Err(_) => 0,
+    }
+}
+
+/// Sizes by path, along with the modification time of the directory they were computed for
+type Sizes = HashMap<String, (Option<SystemTime>, u64)>;
+
+/// The total sizes of directories, computed one after the other on a thread of their own
+#[derive(Default)]
+pub struct DirSizes {
+    sizes: Arc<Mutex<Sizes>>,
+    /// The modification time of the directories already asked for, each is only computed once until it changes
+    requested: HashMap<String, Option<SystemTime>>,
+    queue: Option<Sender<(String, Option<SystemTime>)>>,
+    /// Whether a size was computed since it was last checked
+    changed: Arc<AtomicBool>,
+}
+
+impl DirSizes {
+
+    /// Returns the total size of the files under a directory, `None` until it is computed
+    pub fn size(&mut self, entry: &FileStat) -> Option<u64> {
+        let modified: Option<SystemTime> = entry.modified();
+        if self.requested.get(entry.path()) != Some(&modified) {
+            self.requested.insert(entry.path().to_string(), modified);
+            let queue: &Sender<(String, Option<SystemTime>)> = self.queue.get_or_insert_with(|| {
+                let (sender, receiver) = mpsc::channel::<(String, Option<SystemTime>)>();
+                let (sizes, changed) = (self.sizes.clone(), self.changed.clone());
+                thread::spawn(move || {
+                    for (path, modified) in receiver {
+                        let size: u64 = disk_usage(Path::new(&path));
+                        sizes.lock().unwrap().insert(path, (modified, size));
+                        changed.store(true, Ordering::SeqCst);
+                    }
+                });
+                sender
+            });
+            let _ = queue.send((entry.path().to_string(), modified));
+        }
+        // The previous size still fits better than nothing while the directory is measured again
+        self.sizes.lock().unwrap().get(entry.path()).map(|(_, size)| *size)
+    }
+
+    /// Returns whether a size was computed since the last call
+    pub fn take_changed(&self) -> bool {
+        self.changed.swap(false, Ordering::SeqCst)
+    }
+
+}
+
+/// How often a watched file is looked at again
+const FILE_WATCH_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Watches a single file for changes, such as the one being previewed, so that logs can be followed as they grow
+#[derive(Default)]
+pub struct FileWatch {
+    path: Option<String>,
+    /// When the file was modified and how big it was when it was last looked at
+    stamp: Option<(Option<SystemTime>, u64)>,
+    checked: Option<Instant>,
+}
+
+impl FileWatch {
+
+    /// Watches `path` rather than the previous file, `None` stops watching
+    pub fn watch(&mut self, path: Option<&str>) {
+        if self.path.as_deref() != path {
+            self.path = path.map(str::to_string);
+            self.stamp = path.and_then(stamp);
+            self.checked = Some(Instant::now());
+        }
+    }
+
+    /// Returns whether the file changed since the last call, it is only looked at from time to time
+    pub fn take_changed(&mut self) -> bool {
+        let Some(path) = &self.path else { return false };
+        if self.checked.is_some_and(|checked| checked.elapsed() < FILE_WATCH_INTERVAL) {
+            return false;
+        }
+        self.checked = Some(Instant::now());
+        let stamp: Option<(Option<SystemTime>, u64)> = stamp(path);
+        let changed: bool = stamp != self.stamp;
+        self.stamp = stamp;
+        changed
+    }
+
+}
+
+/// When a file was modified and how big it is, appending to it within the precision of the clock changes its size
+fn stamp(path: &str) -> Option<(Option<SystemTime>, u64)> {
+    fs::metadata(path).ok().map(|meta| (meta.modified().ok(), meta.len()))
+}
+
+/// Lazily computed details about entries, refreshed whenever an entry gets modified
+#[derive(Default)]
+pub struct InfoCache {
+    dirs: HashMap<String, (Option<SystemTime>, Option<DirInfo>)>,
+    kinds: HashMap<String, (Option<SystemTime>, &'static str)>,
+    previews: HashMap<String, (Option<SystemTime>, Vec<String>)>,
+    readable: HashMap<String, (Option<SystemTime>, bool)>,
+    snippets: HashMap<String, (Option<SystemTime>, Option<String>)>,
+}
+
+impl InfoCache {
+
+    /// Returns the summary of a directory
+    pub fn dir(&mut self, entry: &FileStat) -> Option<&DirInfo> {
+        let modified: Option<SystemTime> = fs::metadata(entry.path()).and_then(|meta| meta.modified()).ok();
+        let cached = self.dirs.entry(entry.path().to_string()).or_insert((None, None));
+        if cached.0.is_none() || cached.0 != modified {
+            *cached = (modified, DirInfo::compute(entry.path()));
+        }
+        cached.1.as_ref()
+    }
+
+    /// Returns the kind of contents of a file, sniffed from its first bytes
+    pub fn kind(&mut self, entry: &FileStat) -> &'static str {
+        let modified: Option<SystemTime> = fs::metadata(entry.path()).and_then(|meta| meta.modified()).ok();
+        let cached = self.kinds.entry(entry.path().to_string()).or_insert((None, ""));
+        if cached.0.is_none() || cached.0 != modified {
+            *cached = (modified, sniff(entry.path()));
+        }
+        cached.1
+    }
+
+    /// Returns whether the entries of a directory can be listed, checked again when a rescan finds its status changed
+    /// (permission changes don't modify it)
+    pub fn readable(&mut self, entry: &FileStat) -> bool {
+        let changed: Option<SystemTime> = entry.changed();
+        let cached = self.readable.entry(entry.path().to_string()).or_insert((None, false));
+        if cached.0.is_none() || cached.0 != changed {
+            *cached = (changed, fs::read_dir(entry.path()).is_ok());
+        }
+        cached.1
+    }
+
+    /// Returns the first lines of a file
+    pub fn preview(&mut self, entry: &FileStat) -> &[String] {
+        let modified: Option<SystemTime> = fs::metadata(entry.path()).and_then(|meta| meta.modified()).ok();
+        let cached = self.previews.entry(entry.path().to_string()).or_insert((None, vec![]));
+        if cached.0.is_none() || cached.0 != modified {
+            *cached = (modified, read_preview(entry.path()));
+        }
+        &cached.1
+    }
+
+    /// Returns the first non-empty line of a small text file
+    pub fn snippet(&mut self, entry: &FileStat) -> Option<&str> {
+        let modified: Option<SystemTime> = fs::metadata(entry.path()).and_then(|meta| meta.modified()).ok();
+        let cached = self.snippets.entry(entry.path().to_string()).or_insert((None, None));
+        if cached.0.is_none() || cached.0 != modified {
+            *cached = (modified, read_snippet(entry));
+        }
+        cached.1.as_deref()
+    }
+
+}
+
+/// Files bigger than this don't get a snippet
+const SNIPPET_MAX_SIZE: u64 = 1024 * 1024;
+/// How much of a file is read to find its first line
+const SNIPPET_BYTES: u64 = 4096;
+
+/// Reads the first non-empty line of a text file, binary files have none
+fn read_snippet(entry: &FileStat) -> Option<String> {
+    if !entry.is_file() || entry.size() > SNIPPET_MAX_SIZE {
+        return None;
+    }
+    let mut head: Vec<u8> = vec![];
+    File::open(entry.path()).and_then(|file| file.take(SNIPPET_BYTES).read_to_end(&mut head)).ok()?;
+    decode(&head, encoding(&head)?).lines()
+        .map(|line| line.replace('\t', " ").chars().filter(|c| !c.is_control()).collect::<String>())
+        .map(|line| line.trim().to_string())
+        .find(|line| !line.is_empty())
+}
+
+/// How much of a file is read to preview it
+const PREVIEW_BYTES: u64 = 64 * 1024;
+
+/// Reads the beginning of a text file as lines ready to be drawn, other files are described instead
+fn read_preview(path: &str) -> Vec<String> {
+    let mut head: Vec<u8> = vec![];
+    if File::open(path).and_then(|file| file.take(PREVIEW_BYTES).read_to_end(&mut head)).is_err() {
+        return vec!["cannot read file".to_string()];
+    }
+    match (sniff(path), encoding(&head)) {
+        ("empty", _) => vec!["empty file".to_string()],
+        (kind, Some(encoding)) if kind.ends_with("text") => decode(&head, encoding).lines()
+            .map(|line| line.replace('\t', "    ").chars().filter(|c| !c.is_control()).collect())
+            .collect(),
+        (kind, _) => vec![format!("{}, no preview", kind)],
+    }
+}
+
+/// How the text of a file is encoded
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Encoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Latin1,
+}
+
+/// Guesses how the text that starts with `head` is encoded, `None` if it isn't text
+///
+/// UTF-16 is only recognized by its byte order mark, text that isn't valid UTF-8 is taken as Latin-1
+/// unless it has control characters that text doesn't have
+fn encoding(head: &[u8]) -> Option<Encoding> {
+    if head.starts_with(b"\xff\xfe") {
+        return Some(Encoding::Utf16Le);
+    }
+    if head.starts_with(b"\xfe\xff") {
+        return Some(Encoding::Utf16Be);
+    }
+    if head.contains(&0) {
+        return None;
+    }
+    match std::str::from_utf8(head) {
+        // A character can be cut at the end of the sample
+        Err(err) if err.error_len().is_some() => {
+            let controls: usize = head.iter().filter(|&&b| (b < 0x20 && !b"\t\n\r\x0c\x1b".contains(&b)) || b == 0x7f).count();
+            (controls * 20 <= head.len()).then_some(Encoding::Latin1)
+        }
+        _ => Some(Encoding::Utf8),
+    }
+}
+
+/// Decodes text encoded with `encoding`, without its byte order mark
+fn decode(bytes: &[u8], encoding: Encoding) -> String {
+    match encoding {
+        Encoding::Utf8 => String::from_utf8_lossy(bytes.strip_prefix(b"\xef\xbb\xbf").unwrap_or(bytes)).to_string(),
+        Encoding::Utf16Le | Encoding::Utf16Be => {
+            let units = bytes[2..].chunks_exact(2).map(|pair| {
+                let pair: [u8; 2] = [pair[0], pair[1]];
+                if encoding == Encoding::Utf16Le { u16::from_le_bytes(pair) } else { u16::from_be_bytes(pair) }
+            });
+            char::decode_utf16(units).map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER)).collect()
+        }
+        // Latin-1 bytes are the first 256 code points
+        Encoding::Latin1 => bytes.iter().map(|&b| b as char).collect(),
+    }
+}
+
+/// Guesses the kind of a file from its magic bytes
+pub fn sniff(path: &str) -> &'static str {
+    let mut head: Vec<u8> = Vec::with_capacity(512);
+    if File::open(path).and_then(|file| file.take(512).read_to_end(&mut head)).is_err() {
+        return "unreadable";
+    }
+    const MAGICS: &[(&[u8], &str)] = &[
+        (b"\x89PNG\r\n\x1a\n", "PNG image"),
+        (b"\xff\xd8\xff", "JPEG image"),
+        (b"GIF8", "GIF image"),
+        (b"%PDF", "PDF document"),
+        (b"PK\x03\x04", "zip archive"),
+        (b"\x1f\x8b", "gzip archive"),
+        (b"7z\xbc\xaf\x27\x1c", "7z archive"),
+        (b"\x7fELF", "ELF executable"),
+        (b"MZ", "Windows executable"),
+        (b"\x00asm", "WebAssembly"),
+        (b"wOF2", "WOFF2 font"),
+    ];
+    if let Some((_, kind)) = MAGICS.iter().find(|(magic, _)| head.starts_with(magic)) {
+        return kind;
+    }
+    if head.is_empty() {
+        return "empty";
+    }
+    match encoding(&head) {
+        Some(Encoding::Utf8) => "text",
+        Some(Encoding::Utf16Le | Encoding::Utf16Be) => "UTF-16 text",
+        Some(Encoding::Latin1) => "Latin-1 text",
+        None => "binary",
+    }
+}
+
+/// Returns how many bytes are available to the user on the filesystem containing `path`
+#[cfg(unix)]
+pub fn free_space(path: &Path) -> Option<u64> {
+    use std::os::unix::ffi::OsStrExt;
+    let path = std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    Some(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+/// Returns how many bytes are available to the user on the volume containing `path`
+#[cfg(windows)]
+pub fn free_space(path: &Path) -> Option<u64> {
+    use std::os::windows::ffi::OsStrExt;
+    extern "system" {
+        fn GetDiskFreeSpaceExW(directory: *const u16, available: *mut u64, total: *mut u64, free: *mut u64) -> i32;
+    }
+    let path: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+    let mut available: u64 = 0;
+    if unsafe { GetDiskFreeSpaceExW(path.as_ptr(), &mut available, std::ptr::null_mut(), std::ptr::null_mut()) } == 0 {
+        return None;
+    }
+    Some(available)
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn free_space(_path: &Path) -> Option<u64> {
+    None
+}
@@ -0,0 +1,145 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+/// The frames of the spinner shown by jobs that don't know how long they take
+pub(crate) const SPINNER: &[char] = &['\u{280b}', '\u{2819}', '\u{2839}', '\u{2838}', '\u{283c}', '\u{2834}', '\u{2826}', '\u{2827}', '\u{2807}', '\u{280f}'];
+/// How long each frame of the spinner is shown, in milliseconds
+pub(crate) const SPINNER_FRAME_MS: u128 = 100;
+/// How long a cancelled job is given to stop before it is left to finish on its own
+const CANCEL_GRACE: Duration = Duration::from_millis(500);
+/// How often a cancelled job is checked on while it is given time to stop
+const CANCEL_TICK: Duration = Duration::from_millis(10);
+
+/// What a job and the interface share while it runs
+#[derive(Default)]
+pub struct Progress {
+    done: AtomicUsize,
+    /// How many steps the work should take, 0 when unknown
+    total: AtomicUsize,
+    cancelled: AtomicBool,
+}
+
+impl Progress {
+
+    /// Counts one more step done
+    pub fn step(&self) {
+        self.done.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Sets how many steps the work takes, once the job has found out
+    pub fn set_total(&self, total: usize) {
+        self.total.store(total, Ordering::Relaxed);
+    }
+
+    /// Returns whether the job should stop as soon as it can
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+}
+
+/// Work running on its own thread, its progress is shown in the footer until it is done
+pub struct Job {
+    label: String,
+    progress: Arc<Progress>,
+    handle: Option<JoinHandle<String>>,
+    started: Instant,
+}
+
+impl Job {
+
+    /// Starts `work`, which counts its steps in the progress it is given and returns the message to report once done,
+    /// `total` is how many steps it takes if known beforehand, 0 otherwise
+    pub fn spawn<F>(label: String, total: usize, work: F) -> Job
+    where F: FnOnce(&Progress) -> String + Send + 'static {
+        let progress: Arc<Progress> = Arc::default();
+        progress.set_total(total);
+        let thread_progress: Arc<Progress> = progress.clone();
+        let handle: JoinHandle<String> = thread::spawn(move || work(&thread_progress));
+        Job { label, progress, handle: Some(handle), started: Instant::now() }
+    }
+
+    /// Returns the message of the job once it is done, only once
+    pub fn finish(&mut self) -> Option<String> {
+        if !self.handle.as_ref()?.is_finished() {
+            return None;
+        }
+        let handle: JoinHandle<String> = self.handle.take()?;
+        Some(handle.join().unwrap_or_else(|_| format!("{} failed", self.label)))
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.handle.as_ref().is_none_or(JoinHandle::is_finished)
+    }
+
+    /// Asks the job to stop, waiting a moment for it to do so without blocking the interface on a stuck job
+    pub fn cancel(&mut self) {
+        self.progress.cancelled.store(true, Ordering::Relaxed);
+        let since: Instant = Instant::now();
+        while !self.is_done() && since.elapsed() < CANCEL_GRACE {
+            thread::sleep(CANCEL_TICK);
+        }
+        // Dropping the handle detaches the thread if it is still running
+        self.handle = None;
+    }
+
+    /// The footer field of the job, `label 12/40`, or a spinner and `label 12` when the total is unknown
+    pub fn describe(&self) -> String {
+        let progress: usize = self.progress.done.load(Ordering::Relaxed);
+        let total: usize = self.progress.total.load(Ordering::Relaxed);
+        if total > 0 {
+            format!("{} {}/{}", self.label, progress.min(total), total)
+        }
+        else {
+            let frame: char = SPINNER[(self.started.elapsed().as_millis() / SPINNER_FRAME_MS) as usize % SPINNER.len()];
+            format!("{} {} {}", frame, self.label, progress)
+        }
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finished_jobs_are_done_before_their_message_is_taken() {
+        let mut job: Job = Job::spawn("copying".to_string(), 0, |_| "copied".to_string());
+        while !job.is_done() {
+            thread::sleep(CANCEL_TICK);
+        }
+        assert_eq!(job.finish().as_deref(), Some("copied"));
+        assert_eq!(job.finish(), None);
+    }
+
+    #[test]
+    fn cancelling_does_not_wait_for_a_stuck_job() {
+        let mut job: Job = Job::spawn("stuck".to_string(), 0, |_| {
+            thread::sleep(Duration::from_secs(10));
+            String::new()
+        });
+        let since: Instant = Instant::now();
+        job.cancel();
+        assert!(since.elapsed() < Duration::from_secs(5));
+        assert!(job.is_done());
+    }
+
+    #[test]
+    fn cancelled_jobs_are_asked_to_stop() {
+        let mut job: Job = Job::spawn("deleting".to_string(), 0, |progress| {
+            while !progress.is_cancelled() {
+                thread::sleep(CANCEL_TICK);
+            }
+            String::new()
+        });
+        let since: Instant = Instant::now();
+        job.cancel();
+        assert!(since.elapsed() < CANCEL_GRACE);
+    }
+}
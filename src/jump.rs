@@ -0,0 +1,131 @@
+use std::{
+    collections::VecDeque,
+    fs,
+    path::{Path, PathBuf, MAIN_SEPARATOR},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// How long an index is used before it is built again in the background
+const REFRESH: Duration = Duration::from_secs(300);
+
+/// The most directories indexed, so that huge trees don't take up all the memory
+const MAX_DIRS: usize = 200_000;
+
+/// How many directories the first build finds between each time they are made available
+const BATCH: usize = 1000;
+
+/// The directories under a root, found on a worker thread so that any of them can be jumped to
+pub struct DirIndex {
+    root: PathBuf,
+    /// The paths of the directories relative to the root
+    dirs: Arc<Mutex<Vec<String>>>,
+    building: Arc<AtomicBool>,
+    /// When the last build started
+    built: Option<Instant>,
+}
+
+impl DirIndex {
+
+    pub fn new(root: PathBuf) -> Self {
+        DirIndex {
+            root,
+            dirs: Arc::default(),
+            building: Arc::default(),
+            built: None,
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Whether the directories are still being looked for
+    pub fn is_building(&self) -> bool {
+        self.building.load(Ordering::SeqCst)
+    }
+
+    /// Gives the directories found so far to `f`
+    pub fn with_dirs<T>(&self, f: impl FnOnce(&[String]) -> T) -> T {
+        f(&self.dirs.lock().unwrap())
+    }
+
+    /// Builds the index again in the background if it was never built or is getting old,
+    /// the previous one is used until the new one is done
+    pub fn refresh(&mut self) {
+        if self.is_building() || self.built.is_some_and(|built| built.elapsed() < REFRESH) {
+            return;
+        }
+        self.built = Some(Instant::now());
+        self.building.store(true, Ordering::SeqCst);
+        let root: PathBuf = self.root.clone();
+        let dirs: Arc<Mutex<Vec<String>>> = self.dirs.clone();
+        let building: Arc<AtomicBool> = self.building.clone();
+        thread::spawn(move || {
+            // The first index fills up as it goes, later ones replace the previous one at once
+            let first: bool = dirs.lock().unwrap().is_empty();
+            let mut found: Vec<String> = vec![];
+            let mut published: usize = 0;
+            // Breadth first, so that the directories closest to the root are there first
+            let mut queue: VecDeque<PathBuf> = VecDeque::from([root.clone()]);
+            while let Some(dir) = queue.pop_front() {
+                let Ok(entries) = fs::read_dir(&dir) else { continue };
+                for entry in entries.flatten() {
+                    // Links aren't followed, they could lead in circles
+                    let is_dir: bool = entry.file_type().is_ok_and(|typ| typ.is_dir());
+                    let hidden: bool = entry.file_name().to_string_lossy().starts_with('.');
+                    if !is_dir || hidden || found.len() >= MAX_DIRS { continue }
+                    let path: PathBuf = entry.path();
+                    found.push(path.strip_prefix(&root).unwrap_or(&path).to_string_lossy().to_string());
+                    queue.push_back(path);
+                }
+                if first && found.len() - published >= BATCH {
+                    dirs.lock().unwrap().extend_from_slice(&found[published..]);
+                    published = found.len();
+                }
+            }
+            if first {
+                dirs.lock().unwrap().extend_from_slice(&found[published..]);
+            }
+            else {
+                *dirs.lock().unwrap() = found;
+            }
+            building.store(false, Ordering::SeqCst);
+        });
+    }
+
+}
+
+/// Scores how well `candidate` matches `query`, whose words all have to appear in order, not necessarily next to each other
+///
+/// Letters next to each other, at the start of words and in the last component of the path count more, matches are
+/// looked for from the end so that the name of the directory comes first, `None` if it doesn't match
+pub fn fuzzy_score(candidate: &str, query: &str) -> Option<i64> {
+    let chars: Vec<char> = candidate.chars().flat_map(char::to_lowercase).collect();
+    let last: usize = chars.iter().rposition(|&c| c == MAIN_SEPARATOR || c == '/').map_or(0, |i| i + 1);
+    let mut score: i64 = -(chars.len() as i64) / 8;
+    for word in query.split_whitespace() {
+        let word: Vec<char> = word.chars().flat_map(char::to_lowercase).collect();
+        let mut end: usize = chars.len();
+        let mut previous: Option<usize> = None;
+        for &q in word.iter().rev() {
+            let i: usize = chars[..end].iter().rposition(|&c| c == q)?;
+            score += 1;
+            if previous == Some(i + 1) { score += 5; }
+            match i.checked_sub(1).map(|before| chars[before]) {
+                None | Some('/') => score += 8,
+                Some(c) if c == MAIN_SEPARATOR => score += 8,
+                Some(' ' | '-' | '_' | '.') => score += 4,
+                _ => {}
+            }
+            if i >= last { score += 3; }
+            previous = Some(i);
+            end = i;
+        }
+    }
+    Some(score)
+}
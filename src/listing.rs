@@ -0,0 +1,132 @@
+use chrono::{
+    DateTime, Local, SecondsFormat,
+};
+use std::{
+    fs,
+    io::{self, Write},
+    path::Path,
+    str::FromStr,
+};
+
+use crate::{is_hidden, FileStat, TREE_INDENT};
+use crate::config::Config;
+use crate::format::{format_time, TimeColumn};
+use crate::pattern::glob_match;
+use crate::sort::sort_entries;
+
+/// How `--list` prints the listing
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ListFormat {
+    /// One line per entry: type, size, modification time and name, separated by tabs
+    Plain,
+    /// An array of objects with the same fields
+    Json,
+}
+
+impl FromStr for ListFormat {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "plain" => Ok(ListFormat::Plain),
+            "json" => Ok(ListFormat::Json),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Prints the entries of the directory at `path` to stdout, sorted and filtered like the interactive listing starts out
+pub fn print(path: &Path, config: &Config) -> io::Result<()> {
+    let mut filez: Vec<FileStat> = fs::read_dir(path)?
+        .flatten()
+        .filter(|entry| config.hidden || !is_hidden(&entry.file_name().to_string_lossy()))
+        .filter(|entry| !config.ignore.iter().any(|glob| glob_match(glob, &entry.file_name().to_string_lossy())))
+        .take(config.max_entries)
+        .map(FileStat::from)
+        .collect();
+    sort_entries(&mut filez, config.sort, false, config.dotfiles, config.group_order);
+    let mut out = io::stdout().lock();
+    match config.list_format {
+        ListFormat::Plain => {
+            for f in &filez {
+                writeln!(out, "{}\t{}\t{}\t{}", kind(f), f.size(), modified(f).unwrap_or_default(), escape_plain(f.file_name()))?;
+            }
+        }
+        ListFormat::Json => {
+            writeln!(out, "[")?;
+            for (i, f) in filez.iter().enumerate() {
+                writeln!(out, "  {{\"name\": {}, \"path\": {}, \"type\": \"{}\", \"size\": {}, \"modified\": {}, \"link\": {}}}{}",
+                    json_string(f.file_name()),
+                    json_string(f.path()),
+                    kind(f),
+                    f.size(),
+                    modified(f).map(|time| json_string(&time)).unwrap_or("null".to_string()),
+                    f.link().map(|target| json_string(&target.to_string_lossy())).unwrap_or("null".to_string()),
+                    if i+1 < filez.len() { "," } else { "" },
+                )?;
+            }
+            writeln!(out, "]")?;
+        }
+    }
+    out.flush()
+}
+
+/// The time column and the size format of the details of a listing written as text
+pub type Details = (TimeColumn, fn(u64) -> String);
+
+/// Writes the listing as it is shown as plain text, one name per line indented by how deep it is in the tree (`depths`),
+/// directories end with a separator
+///
+/// With `details`, the names are followed by the size formatted with it and the time of the column
+pub fn as_text(filez: &[FileStat], depths: &[usize], details: Option<Details>) -> String {
+    let names: Vec<String> = filez.iter().enumerate()
+        .map(|(i, f)| format!("{}{}{}", " ".repeat(depths.get(i).copied().unwrap_or(0) * TREE_INDENT), f.file_name(), if f.is_dir() { std::path::MAIN_SEPARATOR_STR } else { "" }))
+        .collect();
+    let Some((time_column, size_format)) = details else {
+        return names.iter().map(|name| format!("{}\n", name)).collect();
+    };
+    let width: usize = names.iter().map(|name| name.chars().count()).max().unwrap_or(0);
+    let sizes: Vec<String> = filez.iter().map(|f| if f.is_file() { size_format(f.size()) } else { "-".to_string() }).collect();
+    let size_width: usize = sizes.iter().map(|size| size.chars().count()).max().unwrap_or(0);
+    filez.iter().zip(names.iter().zip(sizes.iter()))
+        .map(|(f, (name, size))| {
+            let time: String = f.metadata().and_then(|meta| time_column.get(&meta)).map(format_time).unwrap_or_default();
+            // Entries without a time don't leave trailing spaces
+            format!("{}\n", format!("{:<width$}  {:>size_width$}  {}", name, size, time).trim_end())
+        })
+        .collect()
+}
+
+/// The type of an entry, links are described by what they point to
+fn kind(f: &FileStat) -> &'static str {
+    if f.is_dir() { "dir" }
+    else if f.is_file() { "file" }
+    else { "other" }
+}
+
+/// The modification time of an entry as an RFC 3339 timestamp
+fn modified(f: &FileStat) -> Option<String> {
+    f.modified().map(|time| DateTime::<Local>::from(time).to_rfc3339_opts(SecondsFormat::Secs, false))
+}
+
+/// Escapes what would break the columns of the plain format
+fn escape_plain(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('\t', "\\t").replace('\n', "\\n")
+}
+
+/// Quotes a string as JSON
+fn json_string(text: &str) -> String {
+    let mut quoted: String = String::from('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
@@ -0,0 +1,157 @@
+use crosscurses::*;
+use std::{
+    collections::HashMap,
+    env,
+    path::Path,
+};
+
+use crate::FileStat;
+
+/// File name colors read from `$LS_COLORS`, the format used by `ls` and `dircolors`
+pub struct LsColors {
+    /// The attributes of each kind of file (`di`, `ln`, `ex`...)
+    types: HashMap<String, chtype>,
+    /// The attributes of names ending with a suffix (`*.tar`), the longest suffix wins
+    suffixes: Vec<(String, chtype)>,
+}
+
+impl LsColors {
+
+    /// Parses `$LS_COLORS`, colors get their own pairs from `first_pair` onwards
+    ///
+    /// Returns `None` if the variable is unset or doesn't define anything usable
+    pub fn from_env(first_pair: i16) -> Option<LsColors> {
+        let spec: String = env::var("LS_COLORS").ok()?;
+        let mut pairs: HashMap<(i16, i16), i16> = HashMap::new();
+        let mut colors: LsColors = LsColors { types: HashMap::new(), suffixes: vec![] };
+        for item in spec.split(':') {
+            let Some((key, codes)) = item.split_once('=') else { continue };
+            // `ln=target` colors links like what they point to
+            let Some(attr) = parse_codes(codes, first_pair, &mut pairs) else { continue };
+            match key.strip_prefix('*') {
+                Some(suffix) => colors.suffixes.push((suffix.to_lowercase(), attr)),
+                None => { colors.types.insert(key.to_string(), attr); }
+            }
+        }
+        colors.suffixes.sort_by_key(|(suffix, _)| std::cmp::Reverse(suffix.len()));
+        (!colors.types.is_empty() || !colors.suffixes.is_empty()).then_some(colors)
+    }
+
+    /// Returns the attributes of the name of an entry, if `$LS_COLORS` has some for it
+    pub fn attr(&self, entry: &FileStat) -> Option<chtype> {
+        let kind: &str = if entry.link().is_some() && !Path::new(entry.path()).exists() {
+            "or"
+        }
+        else if entry.link().is_some() && self.types.contains_key("ln") {
+            "ln"
+        }
+        else if entry.is_dir() {
+            "di"
+        }
+        else if entry.is_file() {
+            if is_executable(entry) && self.types.contains_key("ex") { "ex" }
+            else {
+                let name: String = entry.file_name().to_lowercase();
+                if let Some((_, attr)) = self.suffixes.iter().find(|(suffix, _)| name.ends_with(suffix.as_str())) {
+                    return Some(*attr);
+                }
+                "fi"
+            }
+        }
+        else {
+            special_kind(entry)
+        };
+        self.types.get(kind).copied()
+    }
+
+}
+
+/// Turns SGR codes (`01;34`, `38;5;208`...) into curses attributes, allocating a pair for the colors
+fn parse_codes(codes: &str, first_pair: i16, pairs: &mut HashMap<(i16, i16), i16>) -> Option<chtype> {
+    let mut attr: chtype = 0;
+    let (mut fg, mut bg): (Option<i16>, Option<i16>) = (None, None);
+    let mut codes = codes.split(';').map(|code| code.parse::<u32>());
+    while let Some(code) = codes.next() {
+        match code.ok()? {
+            0 => {}
+            1 => attr |= A_BOLD,
+            2 => attr |= A_DIM,
+            4 => attr |= A_UNDERLINE,
+            5 => attr |= A_BLINK,
+            7 => attr |= A_REVERSE,
+            code @ 30..=37 => fg = Some((code - 30) as i16),
+            code @ 40..=47 => bg = Some((code - 40) as i16),
+            code @ 90..=97 => { fg = Some((code - 90) as i16); attr |= A_BOLD; }
+            code @ 100..=107 => bg = Some((code - 100) as i16),
+            code @ (38 | 48) => {
+                // Extended colors, only the 256 color palette can be shown
+                let color: Option<i16> = match codes.next()?.ok()? {
+                    5 => color_256(codes.next()?.ok()?),
+                    2 => { codes.next(); codes.next(); codes.next(); None }
+                    _ => None,
+                };
+                if code == 38 { fg = color.or(fg) } else { bg = color.or(bg) }
+            }
+            _ => {}
+        }
+    }
+    if fg.is_some() || bg.is_some() {
+        let key: (i16, i16) = (fg.unwrap_or(COLOR_WHITE), bg.unwrap_or(COLOR_BLACK));
+        let next: i16 = first_pair + pairs.len() as i16;
+        let pair: Option<i16> = match pairs.get(&key) {
+            Some(pair) => Some(*pair),
+            None if (next as i32) < COLOR_PAIRS() => {
+                init_pair(next, key.0, key.1);
+                pairs.insert(key, next);
+                Some(next)
+            }
+            None => None,
+        };
+        if let Some(pair) = pair {
+            attr |= COLOR_PAIR(pair as chtype);
+        }
+    }
+    Some(attr)
+}
+
+/// Maps a color of the 256 color palette onto what the terminal can show
+fn color_256(color: u32) -> Option<i16> {
+    if COLORS() >= 256 && color < 256 {
+        Some(color as i16)
+    }
+    else if color < 16 {
+        Some((color % 8) as i16)
+    }
+    else {
+        None
+    }
+}
+
+#[cfg(unix)]
+fn is_executable(entry: &FileStat) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    entry.metadata().is_some_and(|meta| meta.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(_entry: &FileStat) -> bool {
+    false
+}
+
+/// Returns the `$LS_COLORS` key of entries that are neither files nor directories
+#[cfg(unix)]
+fn special_kind(entry: &FileStat) -> &'static str {
+    use std::os::unix::fs::FileTypeExt;
+    match entry.metadata().map(|meta| meta.file_type()) {
+        Some(typ) if typ.is_fifo() => "pi",
+        Some(typ) if typ.is_socket() => "so",
+        Some(typ) if typ.is_block_device() => "bd",
+        Some(typ) if typ.is_char_device() => "cd",
+        _ => "no",
+    }
+}
+
+#[cfg(not(unix))]
+fn special_kind(_entry: &FileStat) -> &'static str {
+    "no"
+}
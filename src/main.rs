@@ -2,29 +2,60 @@ use crosscurses::*;
 use std::{
     env::{
         args as cmdargs,
-        consts,
         current_dir,
+        var as env_var,
+        var_os as env_var_os,
     },
     fs:: {
         self,
         DirEntry, Metadata,
     },
+    io,
     path::*,
-    process::Command,
     sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
         Arc,
+        Condvar,
         Mutex,
     },
     thread,
-    vec, 
-    time::{ Duration, UNIX_EPOCH }, 
-    collections::HashMap,
-};
-use chrono::{
-    DateTime, Local,
+    process,
+    time::{ Duration, Instant, SystemTime }, 
+    collections::{HashMap, HashSet},
 };
 use iota::iota;
 
+mod archive;
+mod clipboard;
+mod config;
+mod format;
+mod git;
+mod info;
+mod job;
+mod jump;
+mod listing;
+mod lscolors;
+mod open;
+mod ops;
+mod owner;
+mod pattern;
+mod prompt;
+mod search;
+mod sort;
+mod tree;
+use config::Config;
+use format::{all_times, format_age, format_short_time, format_time, grouped_bytes, human_size, permissions, size_bar, split_extension, Column, DateFormat, Highlight, TimeColumn};
+use info::{disk_usage, free_space, DirSizes, FileWatch, InfoCache};
+use job::{Job, SPINNER, SPINNER_FRAME_MS};
+use jump::DirIndex;
+use lscolors::LsColors;
+use owner::Owners;
+use pattern::{glob_match, NamePattern};
+use prompt::{confirm, confirm_changing_list, confirm_list, fuzzy_pick, pick, prompt, prompt_path};
+use search::Search;
+use sort::{sort_entries, Dotfiles, GroupOrder, SortMode};
+use tree::Tree;
+
 struct Icon<'a> {
     m : fn(&str,FileStat) -> bool,
     icon : &'a str,
@@ -36,6 +67,8 @@ iota! {
     , FILE_COLOR_PAIR_DIR
     , FILE_COLOR_PAIR_FILE
     , FILE_COLOR_PAIR_EXTRA
+    , FILE_COLOR_PAIR_LINK
+    , FILE_COLOR_PAIR_BROKEN_LINK
 
     , ICON_COLOR_PAIR_RUST
     , ICON_COLOR_PAIR_GIT
@@ -47,6 +80,14 @@ iota! {
     , ICON_COLOR_PAIR_HTML
     , ICON_COLOR_PAIR_CSS
     , ICON_COLOR_PAIR_FONT
+
+    , SELECTION_COLOR_PAIR
+
+    , AGE_COLOR_PAIR_RECENT
+    , AGE_COLOR_PAIR_WEEK
+
+    // The pairs of $LS_COLORS come after every built-in one
+    , LS_COLORS_FIRST_PAIR
 }
 
 const ICONS: &[Icon] = &[
@@ -117,25 +158,37 @@ const ICONS: &[Icon] = &[
     },
 ];
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 /// Stores information about a file and provides some small helpers
 struct FileStat {
     typ: u32,
     path: String,
     file_name: String,
-
+    size: u64,
+    modified: Option<SystemTime>,
+    /// When the file or its permissions last changed
+    changed: Option<SystemTime>,
+    link: Option<PathBuf>,
+    links: Option<u64>,
 }
-impl Into<FileStat> for DirEntry {
-    fn into(self) -> FileStat {
-        self.path().into()
+impl From<DirEntry> for FileStat {
+    fn from(entry: DirEntry) -> FileStat {
+        entry.path().into()
     }
 }
-impl Into<FileStat> for PathBuf {
-    fn into(self) -> FileStat {
+impl From<PathBuf> for FileStat {
+    fn from(path: PathBuf) -> FileStat {
+        let meta: Option<Metadata> = fs::metadata(&path).ok();
         FileStat {
-            typ: 0 | (if self.is_dir() {FileStat::TYPE_DIR} else {0}) | (if self.is_file() {FileStat::TYPE_FILE} else {0}),
-            path: self.to_str().unwrap().to_string(),
-            file_name: self.file_name().unwrap().to_str().unwrap().to_string()
+            typ: (if meta.as_ref().is_some_and(Metadata::is_dir) {FileStat::TYPE_DIR} else {0}) | (if meta.as_ref().is_some_and(Metadata::is_file) {FileStat::TYPE_FILE} else {0}),
+            path: path.to_str().unwrap().to_string(),
+            // Roots don't have a name of their own
+            file_name: path.file_name().map(|name| name.to_str().unwrap()).unwrap_or(path.to_str().unwrap()).to_string(),
+            size: meta.as_ref().map(Metadata::len).unwrap_or(0),
+            modified: meta.as_ref().and_then(|meta| meta.modified().ok()),
+            changed: meta.as_ref().and_then(status_changed),
+            link: fs::read_link(&path).ok(),
+            links: meta.as_ref().and_then(hard_links),
         }   
     }
 }
@@ -160,6 +213,26 @@ impl FileStat {
     pub fn file_name(&self) -> &str {
         self.file_name.as_str()
     }
+    /// Returns the size of the file in bytes, as of when it was listed
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+    /// Returns when the file was last modified, as of when it was listed
+    pub fn modified(&self) -> Option<SystemTime> {
+        self.modified
+    }
+    /// Returns when the file, its permissions or its owner last changed, as of when it was listed
+    pub fn changed(&self) -> Option<SystemTime> {
+        self.changed
+    }
+    /// Returns where the file points to if it is a symbolic link, as written in the link
+    pub fn link(&self) -> Option<&Path> {
+        self.link.as_deref()
+    }
+    /// Returns how many names the file has (hard links), as of when it was listed
+    pub fn links(&self) -> Option<u64> {
+        self.links
+    }
     /// Returns a new FileStat of the parent of the file
     pub fn parent(&self) -> FileStat {
         let temp: PathBuf = PathBuf::from(self.path.as_str());
@@ -167,48 +240,325 @@ impl FileStat {
         parent.into()
     }
 
-    /// Returns the metadata of the file
-    pub fn metadata(&self) -> Metadata {
-        fs::metadata(self.path()).unwrap()
+    /// Returns the metadata of the file, if it still exists
+    pub fn metadata(&self) -> Option<Metadata> {
+        fs::metadata(self.path()).ok()
     }
 
 }
 
+/// Returns the hard link count of a file (Unix)
+#[cfg(unix)]
+fn hard_links(meta: &Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Some(meta.nlink())
+}
+
+#[cfg(not(unix))]
+fn hard_links(_meta: &Metadata) -> Option<u64> {
+    None
+}
+
+/// Returns when the file last changed, permissions included (Unix), or when it was last modified elsewhere
+#[cfg(unix)]
+fn status_changed(meta: &Metadata) -> Option<SystemTime> {
+    use std::os::unix::fs::MetadataExt;
+    let since_epoch: Duration = Duration::new(u64::try_from(meta.ctime()).ok()?, meta.ctime_nsec() as u32);
+    Some(SystemTime::UNIX_EPOCH + since_epoch)
+}
+
+#[cfg(not(unix))]
+fn status_changed(meta: &Metadata) -> Option<SystemTime> {
+    meta.modified().ok()
+}
+
+/// The sort mode and whether it is reversed, by directory
+type DirSorts = HashMap<PathBuf, (SortMode, bool)>;
+
 #[derive(Clone)]
 struct FileWatcher {
     path: Arc<Mutex<PathBuf>>,
-    path2: Arc<Mutex<PathBuf>>,
+    /// Bumped each time the path changes, a listing is only published for the last one
+    generation: Arc<AtomicU64>,
+    /// The directory of the listing, `None` until the first one is published
+    path2: Arc<Mutex<Option<PathBuf>>>,
+    /// The generation of the last published listing, which navigating waits for
+    listed: Arc<(Mutex<u64>, Condvar)>,
     filez: Arc<Mutex<Vec<FileStat>>>,
+    total: Arc<Mutex<usize>>,
+    /// The name and modification time of the most recently modified entry
+    newest: Arc<Mutex<Option<(String, SystemTime)>>>,
+    /// The size of the largest file of the listing
+    largest: Arc<Mutex<u64>>,
+    sort: Arc<Mutex<SortMode>>,
+    reverse: Arc<Mutex<bool>>,
+    hidden: Arc<Mutex<bool>>,
+    /// Globs of the names left out of the listing
+    ignore: Arc<Mutex<Vec<String>>>,
+    dotfiles: Arc<Mutex<Dotfiles>>,
+    groups: Arc<Mutex<GroupOrder>>,
+    notice: Arc<Mutex<Option<String>>>,
+    /// Whether the watcher published something different since it was last checked
+    changed: Arc<AtomicBool>,
+    /// When the files whose size or modification time changed between scans last did so, by path
+    changing: Arc<Mutex<HashMap<String, Instant>>>,
+    /// The sort mode and order chosen in each directory, if they are remembered
+    dir_sorts: Arc<Mutex<Option<DirSorts>>>,
+    /// The sort mode of directories without one of their own
+    default_sort: SortMode,
 }
 impl FileWatcher {
 
-    pub fn new(path: Option<String>) -> Self {
+    pub fn new(path: PathBuf, sort: SortMode) -> Self {
         FileWatcher {
-            path: Arc::from(Mutex::from(path.map(PathBuf::from).unwrap_or_else(|| current_dir().unwrap()))),
-            path2: Arc::from(Mutex::from(PathBuf::from(""))),
-            filez: Arc::default()
+            path: Arc::from(Mutex::from(path)),
+            generation: Arc::from(AtomicU64::new(1)),
+            path2: Arc::default(),
+            listed: Arc::default(),
+            filez: Arc::default(),
+            total: Arc::default(),
+            newest: Arc::default(),
+            largest: Arc::default(),
+            sort: Arc::from(Mutex::from(sort)),
+            reverse: Arc::default(),
+            hidden: Arc::from(Mutex::from(true)),
+            ignore: Arc::default(),
+            dotfiles: Arc::from(Mutex::from(Dotfiles::Mixed)),
+            groups: Arc::from(Mutex::from(GroupOrder::DirsFirst)),
+            notice: Arc::default(),
+            changed: Arc::default(),
+            changing: Arc::default(),
+            dir_sorts: Arc::default(),
+            default_sort: sort,
         }
     }
 
     pub fn path(&self) -> PathBuf {
         self.path.lock().unwrap().clone()
     }
-    pub fn set_path(&self, pathfn: Box<dyn Fn(&mut PathBuf)->()>) {
-        pathfn(&mut *self.path.lock().unwrap());
+    /// Returns the generation of the path once changed
+    pub fn set_path(&self, pathfn: impl FnOnce(&mut PathBuf)) -> u64 {
+        let mut path = self.path.lock().unwrap();
+        let old: PathBuf = path.clone();
+        pathfn(&mut path);
+        if *path != old {
+            self.generation.fetch_add(1, Ordering::SeqCst);
+        }
+        self.generation()
+    }
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::SeqCst)
+    }
+    /// Returns the path along with its generation, read together
+    pub fn path_generation(&self) -> (PathBuf, u64) {
+        let path = self.path.lock().unwrap();
+        (path.clone(), self.generation())
     }
 
     pub fn filez(&self) -> Vec<FileStat> {
         self.filez.lock().unwrap().clone()
     }
-    pub fn set_filez(&self, filezfn: Box<dyn Fn(&mut Vec<FileStat>)->()>) {
-        filezfn(&mut *self.filez.lock().unwrap());
+    pub fn set_filez(&self, filezfn: impl FnOnce(&mut Vec<FileStat>)) {
+        filezfn(&mut self.filez.lock().unwrap());
     }
 
-    pub fn path2(&self) -> PathBuf {
-        self.path2.lock().unwrap().clone()
+    /// Returns the amount of entries in the directory, which can be more than what `filez` holds
+    pub fn total(&self) -> usize {
+        *self.total.lock().unwrap()
+    }
+    pub fn set_total(&self, total: usize) {
+        *self.total.lock().unwrap() = total;
+    }
+
+    /// Returns the name and modification time of the most recently modified entry of the listing
+    pub fn newest(&self) -> Option<(String, SystemTime)> {
+        self.newest.lock().unwrap().clone()
+    }
+
+    /// Returns the size of the largest file of the listing
+    pub fn largest(&self) -> u64 {
+        *self.largest.lock().unwrap()
+    }
+
+    pub fn sort(&self) -> SortMode {
+        *self.sort.lock().unwrap()
+    }
+    /// Changes the sort mode, the current listing is sorted again right away
+    pub fn set_sort(&self, sort: SortMode) {
+        *self.sort.lock().unwrap() = sort;
+        self.resort();
+        self.save_sort();
+    }
+
+    pub fn reverse(&self) -> bool {
+        *self.reverse.lock().unwrap()
+    }
+    /// Changes whether the order is reversed, the current listing is sorted again right away
+    pub fn set_reverse(&self, reverse: bool) {
+        *self.reverse.lock().unwrap() = reverse;
+        self.resort();
+        self.save_sort();
+    }
+
+    /// Returns whether hidden entries (dotfiles) are listed
+    pub fn hidden(&self) -> bool {
+        *self.hidden.lock().unwrap()
+    }
+    /// Changes whether hidden entries are listed, they are removed from the current listing right away
+    /// but only come back with the next scan
+    pub fn set_hidden(&self, hidden: bool) {
+        *self.hidden.lock().unwrap() = hidden;
+        if !hidden {
+            self.set_filez(|filez: &mut Vec<FileStat>|{filez.retain(|f| !is_hidden(f.file_name()));});
+        }
+    }
+
+    /// Changes the globs of the names left out of the listing, they are removed from the current listing right away
+    /// but entries that aren't ignored anymore only come back with the next scan
+    pub fn set_ignore(&self, ignore: Vec<String>) {
+        *self.ignore.lock().unwrap() = ignore;
+        self.set_filez(|filez: &mut Vec<FileStat>|{filez.retain(|f| self.is_listed(f.file_name()));});
+    }
+
+    /// Returns whether an entry named `file_name` is listed, it could be hidden or ignored
+    pub fn is_listed(&self, file_name: &str) -> bool {
+        (self.hidden() || !is_hidden(file_name)) && !self.ignore.lock().unwrap().iter().any(|glob| glob_match(glob, file_name))
+    }
+
+    /// Returns where dotfiles are placed in the listing
+    pub fn dotfiles(&self) -> Dotfiles {
+        *self.dotfiles.lock().unwrap()
+    }
+    /// Changes where dotfiles are placed, the current listing is sorted again right away
+    pub fn set_dotfiles(&self, dotfiles: Dotfiles) {
+        *self.dotfiles.lock().unwrap() = dotfiles;
+        self.resort();
+    }
+
+    /// Returns how directories and files are grouped in the listing
+    pub fn groups(&self) -> GroupOrder {
+        *self.groups.lock().unwrap()
+    }
+    /// Changes how directories and files are grouped, the current listing is sorted again right away
+    pub fn set_groups(&self, groups: GroupOrder) {
+        *self.groups.lock().unwrap() = groups;
+        self.resort();
+    }
+
+    /// Sorts entries with the current sort mode, order, dotfile placement and grouping
+    pub fn sort_listing(&self, filez: &mut [FileStat]) {
+        sort_entries(filez, self.sort(), self.reverse(), self.dotfiles(), self.groups());
+    }
+    /// Sorts the current listing again after its order changed
+    fn resort(&self) {
+        self.set_filez(|filez: &mut Vec<FileStat>|{self.sort_listing(filez);});
+    }
+
+    /// Makes every directory keep its own sort mode and order
+    pub fn remember_sorts(&self) {
+        *self.dir_sorts.lock().unwrap() = Some(HashMap::new());
+    }
+    /// Remembers the sort mode and order of the current directory, if enabled
+    fn save_sort(&self) {
+        if let Some(dir_sorts) = self.dir_sorts.lock().unwrap().as_mut() {
+            dir_sorts.insert(self.path(), (self.sort(), self.reverse()));
+        }
+    }
+    /// Switches to the sort mode and order remembered for `path`, if enabled,
+    /// directories that weren't sorted yet get the default ones
+    pub fn restore_sort(&self, path: &Path) {
+        let restored: Option<(SortMode, bool)> = self.dir_sorts.lock().unwrap().as_ref()
+            .map(|dir_sorts| dir_sorts.get(path).copied().unwrap_or((self.default_sort, false)));
+        if let Some((sort, reverse)) = restored {
+            *self.sort.lock().unwrap() = sort;
+            *self.reverse.lock().unwrap() = reverse;
+        }
+    }
+
+    /// Takes the last message the watcher left for the user
+    pub fn take_notice(&self) -> Option<String> {
+        self.notice.lock().unwrap().take()
+    }
+    pub fn set_notice(&self, notice: String) {
+        *self.notice.lock().unwrap() = Some(notice);
+    }
+
+    /// Replaces the listing with the entries of `path`, unless another directory was browsed to while it was read,
+    /// `generation` being the one `path` was read at
+    ///
+    /// Returns whether the listing was replaced
+    pub fn publish(&self, path: &Path, generation: u64, filez: Vec<FileStat>, total: usize) -> bool {
+        // Held until the listing is replaced, so that navigating can't happen in between
+        let _current = self.path.lock().unwrap();
+        if self.generation() != generation {
+            return false;
+        }
+        let listed_before: bool = self.path2().as_deref() == Some(path);
+        if !listed_before || self.total() != total || *self.filez.lock().unwrap() != filez {
+            self.changed.store(true, Ordering::SeqCst);
+        }
+        // Files that are being written to change from one scan to the next
+        let mut changing = self.changing.lock().unwrap();
+        if listed_before {
+            let previous: HashMap<String, (u64, Option<SystemTime>)> = self.filez.lock().unwrap().iter()
+                .filter(|f| f.is_file())
+                .map(|f| (f.path().to_string(), (f.size(), f.modified())))
+                .collect();
+            for f in &filez {
+                if previous.get(f.path()).is_some_and(|&stamp| stamp != (f.size(), f.modified())) {
+                    changing.insert(f.path().to_string(), Instant::now());
+                }
+            }
+            changing.retain(|_, changed| changed.elapsed() < CHANGING_HOLD);
+        }
+        else {
+            changing.clear();
+        }
+        drop(changing);
+        *self.newest.lock().unwrap() = filez.iter()
+            .filter_map(|f| Some((f.file_name().to_string(), f.modified()?)))
+            .max_by_key(|(_, modified)| *modified);
+        *self.largest.lock().unwrap() = filez.iter().filter(|f| f.is_file()).map(FileStat::size).max().unwrap_or(0);
+        let (listed, published) = &*self.listed;
+        let mut listed = listed.lock().unwrap();
+        self.set_filez(move|nfilez: &mut Vec<FileStat>|{*nfilez = filez;});
+        self.set_total(total);
+        *self.path2.lock().unwrap() = Some(path.to_path_buf());
+        *listed = generation;
+        published.notify_all();
+        true
+    }
+
+    /// Waits at most `timeout` for the listing of `generation`,
+    /// past which the listing of the previous directory is dropped rather than shown under the new one
+    ///
+    /// Returns whether the listing came in time
+    pub fn wait_listing(&self, generation: u64, timeout: Duration) -> bool {
+        let (listed, published) = &*self.listed;
+        let (listed, _) = published.wait_timeout_while(listed.lock().unwrap(), timeout, |listed| *listed < generation).unwrap();
+        if *listed < generation {
+            self.set_filez(Vec::clear);
+            self.set_total(0);
+            return false;
+        }
+        true
+    }
+
+    /// Returns the paths of the files that changed between the last scans, which are likely being written to
+    pub fn changing(&self) -> HashSet<String> {
+        self.changing.lock().unwrap().iter()
+            .filter(|(_, changed)| changed.elapsed() < CHANGING_HOLD)
+            .map(|(path, _)| path.clone())
+            .collect()
+    }
+
+    /// Returns whether the listing changed since the last call
+    pub fn take_changed(&self) -> bool {
+        self.changed.swap(false, Ordering::SeqCst)
     }
-    pub fn set_path2(&self, pathfn: Box<dyn Fn(&mut PathBuf)->()>) {
-        pathfn(&mut *self.path2.lock().unwrap());
+
+    pub fn path2(&self) -> Option<PathBuf> {
+        self.path2.lock().unwrap().clone()
     }
 
 }
@@ -220,224 +570,2168 @@ struct View {
     scroll: i32
 }
 
-fn main() {
-    let mut args = cmdargs();
+/// Turns a path into a clean absolute one for display, relative paths and `..` components are resolved
+fn normalize_path(path: PathBuf) -> PathBuf {
+    let normalized: PathBuf = match fs::canonicalize(&path) {
+        Ok(canonical) => canonical,
+        Err(_) => return std::path::absolute(&path).unwrap_or(path),
+    };
+    // Windows gives verbatim paths (`\\?\C:\...`), which are only needed for very long paths
+    match normalized.to_str().and_then(|p| p.strip_prefix(r"\\?\")) {
+        Some(stripped) if !stripped.starts_with("UNC") && stripped.len() < 260 => PathBuf::from(stripped),
+        _ => normalized,
+    }
+}
 
-    let win: Window = initscr();
+/// Moves the cursor by `amount` entries, keeping it within the listing
+///
+/// Going past an end either stops there or, with `wrap`, continues from the other end
+fn move_selection(selected: i32, amount: i32, len: usize, wrap: bool) -> i32 {
+    if len == 0 { return 0; }
+    if wrap {
+        (selected + amount).rem_euclid(len as i32)
+    }
+    else {
+        (selected + amount).clamp(0, len as i32 - 1)
+    }
+}
 
-    win.keypad(true);
-    win.nodelay(true);
-    mousemask(ALL_MOUSE_EVENTS, std::ptr::null_mut());
-    noecho();
-    curs_set(0);
+/// Returns the index of the first folder (or file) below `selected`, going on from the top with `wrap`
+fn next_of_type(filez: &[FileStat], selected: i32, dirs: bool, wrap: bool) -> Option<usize> {
+    let start: usize = (selected + 1).max(0) as usize;
+    let below = start.min(filez.len())..filez.len();
+    let above = 0..if wrap { start.min(filez.len()) } else { 0 };
+    below.chain(above).find(|&i| if dirs { filez[i].is_dir() } else { filez[i].is_file() })
+}
 
-    start_color();
+/// Returns whether an entry is hidden by default, which is the case of dotfiles
+fn is_hidden(file_name: &str) -> bool {
+    file_name.starts_with('.')
+}
 
-    init_pair(FILE_COLOR_PAIR_DIR, COLOR_BLUE, COLOR_BLACK);
-    init_pair(FILE_COLOR_PAIR_FILE, COLOR_WHITE, COLOR_BLACK);
-    init_pair(FILE_COLOR_PAIR_EXTRA, COLOR_YELLOW, COLOR_BLACK);
+/// Returns the directory above `path`, roots are their own parent
+///
+/// The path is only cut, so going up from a symbolic link leads back where the link is
+fn parent_of(path: &Path) -> PathBuf {
+    let mut parent: PathBuf = path.to_path_buf();
+    parent.pop();
+    parent
+}
 
-    init_pair(ICON_COLOR_PAIR_GIT, COLOR_YELLOW, COLOR_BLACK);
-    init_pair(ICON_COLOR_PAIR_RUST, COLOR_YELLOW, COLOR_BLACK);
-    init_pair(ICON_COLOR_PAIR_CONFIG, COLOR_CYAN, COLOR_BLACK);
-    init_pair(ICON_COLOR_PAIR_LOCK, COLOR_YELLOW, COLOR_BLACK);
-    init_pair(ICON_COLOR_PAIR_JSON, COLOR_YELLOW, COLOR_BLACK);
-    init_pair(ICON_COLOR_PAIR_JS, COLOR_GREEN, COLOR_BLACK);
-    init_pair(ICON_COLOR_PAIR_SVG, COLOR_RED, COLOR_BLACK);
-    init_pair(ICON_COLOR_PAIR_CSS, COLOR_BLUE, COLOR_BLACK);
-    init_pair(ICON_COLOR_PAIR_HTML, COLOR_YELLOW, COLOR_BLACK);
-    init_pair(ICON_COLOR_PAIR_FONT, COLOR_RED, COLOR_BLACK);
+/// Returns where going up from `path` leads, on Windows the roots of drives lead to the list of drives
+fn up_from(path: &Path) -> PathBuf {
+    if cfg!(windows) && path.parent().is_none() {
+        return PathBuf::new();
+    }
+    parent_of(path)
+}
 
-    let file_watcher: FileWatcher = FileWatcher::new(args.nth(1));
-    
-    let mut selected: i32 = 0;
-    let mut selected_hist: HashMap<String,View> = HashMap::new();
-    let mut scroll: i32 = 0;
+/// Returns whether `path` stands for the list of drives, which is above the roots of drives on Windows
+fn is_drive_list(path: &Path) -> bool {
+    cfg!(windows) && path.as_os_str().is_empty()
+}
+
+/// Lists the roots of the drives that are present (`C:\`, `D:\`...)
+#[cfg(windows)]
+fn list_drives() -> Vec<FileStat> {
+    extern "system" {
+        fn GetLogicalDrives() -> u32;
+    }
+    let drives: u32 = unsafe { GetLogicalDrives() };
+    (0..26u8).filter(|i| drives & (1 << i) != 0)
+        .map(|i| FileStat::from(PathBuf::from(format!("{}:\\", (b'A' + i) as char))))
+        .collect()
+}
+
+/// Resolves the `.` and `..` components of a path the same way going up does, without following links
+fn resolve_dots(path: &Path) -> PathBuf {
+    let mut resolved: PathBuf = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => resolved = parent_of(&resolved),
+            component => resolved.push(component),
+        }
+    }
+    resolved
+}
+
+/// Returns the rows of the listing as they are shown, the contents of expanded directories included in the tree view,
+/// along with how deep each row is (nothing outside of the tree view)
+fn shown_entries(file_watcher: &FileWatcher, tree: &mut Tree) -> (Vec<FileStat>, Vec<usize>) {
+    let filez: Vec<FileStat> = file_watcher.filez();
+    if tree.enabled {
+        tree.flatten(filez, &|children| file_watcher.sort_listing(children), &|f| file_watcher.is_listed(f.file_name()))
+    }
+    else {
+        (filez, vec![])
+    }
+}
 
-    let thread_file_watcher: FileWatcher = file_watcher.clone();
+/// Starts the thread that lists the directory of the watcher, again every `refresh` or as soon as it changes
+fn spawn_watcher(thread_file_watcher: FileWatcher, max_entries: usize, refresh: Duration) {
     thread::spawn(move || {
-        loop {
-            thread::sleep(Duration::from_millis(100));
-            let p = thread_file_watcher.path();
+        let mut last_generation: u64 = 0;
+        'scan: loop {
+            // Going to another directory doesn't wait for the next refresh
+            let since: Instant = Instant::now();
+            while since.elapsed() < refresh && thread_file_watcher.generation() == last_generation {
+                thread::sleep(WATCHER_TICK.min(refresh));
+            }
+            let (p, generation) = thread_file_watcher.path_generation();
+            // The first scan of a directory is shown as it goes, rescans only replace the listing once done
+            let fresh: bool = generation != last_generation;
+            #[cfg(windows)]
+            if is_drive_list(&p) {
+                let drives: Vec<FileStat> = list_drives();
+                let total: usize = drives.len();
+                thread_file_watcher.publish(&p, generation, drives, total);
+                last_generation = generation;
+                continue;
+            }
             let mut filez: Vec<FileStat> = vec![];
-            if let Ok(entries) = fs::read_dir(p.as_path()) {
-                for entry in entries {
-                    if let Ok(entry) = entry {
+            let mut total: usize = 0;
+            let entries = match fs::read_dir(p.as_path()) {
+                Ok(entries) => Some(entries),
+                Err(_) if !p.exists() => {
+                    // The directory was removed, the closest remaining ancestor is shown instead
+                    if let Some(ancestor) = p.ancestors().skip(1).find(|ancestor| ancestor.is_dir()) {
+                        let ancestor: PathBuf = ancestor.to_path_buf();
+                        thread_file_watcher.publish(&p, generation, vec![], 0);
+                        thread_file_watcher.set_notice(format!("{} no longer exists, moved up to {}", p.display(), ancestor.display()));
+                        thread_file_watcher.set_path(|path: &mut PathBuf|{
+                            // Unless the user went somewhere else in the meantime
+                            if *path == p { *path = ancestor; }
+                        });
+                        continue;
+                    }
+                    None
+                }
+                Err(_) => None,
+            };
+            if let Some(entries) = entries {
+                for entry in entries.flatten() {
+                    if !thread_file_watcher.is_listed(&entry.file_name().to_string_lossy()) { continue }
+                    // Past the limit, entries are only counted
+                    if total < max_entries {
                         filez.push(entry.into());
                     }
+                    total += 1;
+                    if total.is_multiple_of(STREAM_BATCH) {
+                        // A long scan is given up as soon as the user goes somewhere else, the new directory is read instead
+                        if thread_file_watcher.generation() != generation {
+                            continue 'scan;
+                        }
+                        if fresh && total <= max_entries {
+                            let mut batch: Vec<FileStat> = filez.clone();
+                            thread_file_watcher.sort_listing(&mut batch);
+                            thread_file_watcher.publish(&p, generation, batch, total);
+                        }
+                    }
                 }
             }
-            filez.sort_by(|a: &FileStat, b : &FileStat| b.is_dir().partial_cmp(&a.is_dir()).unwrap() );
-            thread_file_watcher.set_filez(Box::new(move|nfilez: &mut Vec<FileStat>|{filez.clone_into(nfilez);}));
-            thread_file_watcher.set_path2(Box::new(move|path2: &mut PathBuf|{*path2=p.clone()}))
+            thread_file_watcher.sort_listing(&mut filez);
+            thread_file_watcher.publish(&p, generation, filez, total);
+            last_generation = generation;
         }
     });
+}
 
-    loop {
+/// Moves the watcher to `target` and waits for its listing,
+/// returns the view to restore, which defaults to the entry we came from when going up
+///
+/// While the listing is locked to `root`, targets outside of it are refused with the message to show
+fn navigate(file_watcher: &FileWatcher, selected_hist: &HashMap<String,View>, root: Option<&Path>, target: PathBuf) -> Result<View, String> {
+    if let Some(root) = root.filter(|root| !target.starts_with(root)) {
+        return Err(format!("locked to {}, press L to unlock", root.display()));
+    }
+    let old_path: PathBuf = file_watcher.path();
+    if target == old_path {
+        return Ok(selected_hist.get(old_path.to_str().unwrap()).copied().unwrap_or(View { selected: 0, scroll: 0 }));
+    }
+    let ntarget: PathBuf = target.clone();
+    // The listing of the target has to come in its own order for the view to be restored
+    file_watcher.restore_sort(&target);
+    let generation: u64 = file_watcher.set_path(move |path: &mut PathBuf|{ *path = ntarget; });
+    // Waits for the first entries of the target, a slow directory is shown empty until they come
+    file_watcher.wait_listing(generation, NAVIGATE_TIMEOUT);
+    Ok(selected_hist.get(target.to_str().unwrap()).copied().unwrap_or_else(||{
+        // Going up selects the directory that was left, drives included
+        for (i, f) in file_watcher.filez().iter().enumerate() {
+            if old_path.starts_with(f.path()) {
+                return View {
+                    selected: i as i32,
+                    scroll: i as i32
+                };
+            }
+        }
+        View { 
+            selected: 0,
+            scroll: 0,
+        }
+    }))
+}
 
-        let path: PathBuf = file_watcher.path();
-        let filez: Vec<FileStat> = file_watcher.filez();
+/// Returns the ancestor of `path` whose component is displayed at column `x` of the header
+fn breadcrumb_at(path: &Path, x: i32) -> Option<PathBuf> {
+    if x < 0 { return None; }
+    path.ancestors()
+        .filter(|ancestor| ancestor.to_string_lossy().chars().count() as i32 > x)
+        .last()
+        .map(Path::to_path_buf)
+}
 
-        win.clear();
+/// Returns the columns of the header taken by the component of `ancestor`, the last one of `ancestor`
+fn breadcrumb_span(ancestor: &Path) -> std::ops::Range<usize> {
+    let end: usize = ancestor.to_string_lossy().chars().count();
+    let start: usize = match ancestor.parent() {
+        // The separator after the parent isn't part of the component, roots already end with one
+        Some(parent) => {
+            let parent: String = parent.to_string_lossy().to_string();
+            parent.chars().count() + if parent.ends_with(is_separator) { 0 } else { 1 }
+        }
+        None => 0,
+    };
+    start.min(end)..end
+}
 
-        win.mvaddstr(0, 0, path.to_str().unwrap());
+/// Returns the home directory of the user
+fn home_dir() -> Option<PathBuf> {
+    env_var_os(if cfg!(windows) { "USERPROFILE" } else { "HOME" })
+        .filter(|home| !home.is_empty())
+        .map(PathBuf::from)
+}
 
-        for i in 0i32..win.get_max_y()-2 {
-            if i+scroll < 0 {continue}
-            if i+scroll >= filez.len() as i32 {break}
-            let entry: &FileStat = &filez[(i+scroll) as usize];
+/// Expands a leading `~` or `~user` to the home directory like shells do, for paths that reach filez unexpanded
+/// (e.g. quoted), the path is kept as it is when the home directory is unknown
+fn expand_tilde(arg: &str) -> PathBuf {
+    expand_tilde_with(arg, |user| if user.is_empty() { home_dir() } else { owner::user_home(user).map(PathBuf::from) })
+}
+
+/// Expands a leading `~` or `~user` with `home_of`, which gives the home directory of a user, of the current one for `""`
+fn expand_tilde_with(arg: &str, home_of: impl Fn(&str) -> Option<PathBuf>) -> PathBuf {
+    let Some(rest) = arg.strip_prefix('~') else { return PathBuf::from(arg) };
+    let (user, rest): (&str, &str) = match rest.find(is_separator) {
+        Some(i) => (&rest[..i], &rest[i+1..]),
+        None => (rest, ""),
+    };
+    match home_of(user) {
+        Some(home) if rest.is_empty() => home,
+        Some(home) => home.join(rest),
+        None => PathBuf::from(arg),
+    }
+}
+
+/// Shows `path` with the `home` prefix replaced by `~` like shells do, other paths are shown as they are
+fn tildify(path: &Path, home: &Path) -> String {
+    match path.strip_prefix(home) {
+        Ok(rest) if rest.as_os_str().is_empty() => "~".to_string(),
+        Ok(rest) => format!("~{}{}", MAIN_SEPARATOR, rest.display()),
+        Err(_) => path.display().to_string(),
+    }
+}
+
+/// Returns whether the directory at `path` can be read and has no entries
+fn is_empty_dir(path: &str) -> bool {
+    fs::read_dir(path).map(|mut entries| entries.next().is_none()).unwrap_or(false)
+}
 
-            win.mv(i+1 as i32,0);
+/// Shortens `text` to `width` columns by cutting its start, an ellipsis marks the cut
+fn truncate_start(text: &str, width: usize) -> String {
+    let len: usize = text.chars().count();
+    if len <= width { return text.to_string(); }
+    if width == 0 { return String::new(); }
+    let kept: String = text.chars().skip(len - width + 1).collect();
+    format!("\u{2026}{}", kept)
+}
+
+/// Draws the last line, `left` is truncated so that it never overlaps the right-aligned `fields`
+fn draw_footer(win: &Window, left: &str, fields: &[String]) {
+    let y: i32 = win.get_max_y()-1;
+    let width: usize = win.get_max_x().max(0) as usize;
+    let right: String = fields.join("  ");
+    let right_len: usize = right.chars().count().min(width);
+    win.mvaddstr(y, 0, truncate_start(left, width.saturating_sub(right_len + 1)));
+    win.mvaddstr(y, (width - right_len) as i32, right.chars().skip(right.chars().count() - right_len).collect::<String>());
+}
+
+/// How many rows the listing gets in a terminal `height` rows high, at most `limit` (`0` for no limit)
+/// and never more than the terminal fits once the header and footer are drawn
+fn body_rows(height: i32, limit: usize) -> i32 {
+    let available: i32 = (height - 2).max(0);
+    if limit == 0 { available } else { available.min(limit as i32) }
+}
+
+/// How many entries are read between each update of a directory being listed for the first time
+const STREAM_BATCH: usize = 1000;
 
-            win.printw(" ");
+/// The width of the info panel, including its border
+const INFO_PANEL_WIDTH: i32 = 36;
 
-            let mut found: bool = false;
-            let file_name =  entry.file_name();
-            for icon in ICONS {
-                if (icon.m)(file_name,entry.to_owned()) {
-                    win.attron(COLOR_PAIR(icon.color as u64));
-                    win.printw(icon.icon);
-                    win.attroff(COLOR_PAIR(icon.color as u64));
-                    found = true;
-                    break;
+/// Draws the details of `entry` in a panel on the right of the listing
+fn draw_info_panel(win: &Window, height: i32, entry: &FileStat, cache: &mut InfoCache, size_format: fn(u64) -> String) {
+    let x: i32 = (win.get_max_x() - INFO_PANEL_WIDTH).max(0);
+    let mut lines: Vec<String> = vec![entry.file_name().to_string(), String::new()];
+    if entry.is_dir() {
+        match cache.dir(entry) {
+            Some(info) => {
+                lines.push(format!("entries  {}", info.children));
+                lines.push(format!("size     {}", size_format(info.size)));
+                if let Some(newest) = &info.newest {
+                    lines.push(format!("newest   {}", newest));
                 }
             }
-            if !found { win.printw("?"); }
-            win.printw(" ");
-            
-            let ft: u64 = {
-                if entry.is_dir() {
-                    FILE_COLOR_PAIR_DIR
-                }
-                else if entry.is_file() {
-                    FILE_COLOR_PAIR_FILE
-                }
-                else {
-                    FILE_COLOR_PAIR_EXTRA
-                }
-            } as u64;
+            None => lines.push("cannot read directory".to_string()),
+        }
+    }
+    else if let Ok(meta) = fs::metadata(entry.path()) {
+        lines.push(format!("size     {}", size_format(meta.len())));
+        let (accessed, modified, created) = all_times(&meta);
+        for (label, time) in [("accessed", accessed), ("modified", modified), ("created ", created)] {
+            lines.push(format!("{} {}", label, time.unwrap_or_else(|| "-".to_string())));
+        }
+        lines.push(format!("perms    {}", permissions(&meta)));
+        lines.push(format!("type     {}", cache.kind(entry)));
+    }
+    lines.push(format!("links    {}", entry.links().map(|links| links.to_string()).unwrap_or("-".to_string())));
+    win.mv(1, x);
+    win.vline(ACS_VLINE(), height);
+    for row in 0..height {
+        win.mv(row+1, x+1);
+        win.clrtoeol();
+        if let Some(line) = lines.get(row as usize) {
+            win.addstr(format!(" {}", line.chars().take((INFO_PANEL_WIDTH-2) as usize).collect::<String>()));
+        }
+    }
+}
+
+/// Draws the first lines of `entry` over the right half of the listing
+fn draw_preview_panel(win: &Window, height: i32, entry: &FileStat, cache: &mut InfoCache) {
+    let x: i32 = win.get_max_x() / 2;
+    let width: usize = (win.get_max_x() - x - 2).max(0) as usize;
+    let lines: &[String] = cache.preview(entry);
+    win.mv(1, x);
+    win.vline(ACS_VLINE(), height);
+    for row in 0..height {
+        win.mv(row+1, x+1);
+        win.clrtoeol();
+        if let Some(line) = lines.get(row as usize) {
+            win.addstr(format!(" {}", line.chars().take(width).collect::<String>()));
+        }
+    }
+}
+
+/// The parts of the interface that can be recolored in the `[colors]` section of the config,
+/// with their color pair and default foreground and background
+pub const COLOR_ROLES: &[(&str, i16, i16, i16)] = &[
+    ("dir", FILE_COLOR_PAIR_DIR, COLOR_BLUE, COLOR_BLACK),
+    ("file", FILE_COLOR_PAIR_FILE, COLOR_WHITE, COLOR_BLACK),
+    ("accent", FILE_COLOR_PAIR_EXTRA, COLOR_YELLOW, COLOR_BLACK),
+    ("link", FILE_COLOR_PAIR_LINK, COLOR_GREEN, COLOR_BLACK),
+    ("broken_link", FILE_COLOR_PAIR_BROKEN_LINK, COLOR_RED, COLOR_BLACK),
+    ("selection", SELECTION_COLOR_PAIR, COLOR_BLACK, COLOR_CYAN),
+    ("age_recent", AGE_COLOR_PAIR_RECENT, COLOR_GREEN, COLOR_BLACK),
+    ("age_week", AGE_COLOR_PAIR_WEEK, COLOR_CYAN, COLOR_BLACK),
+
+    ("icon_git", ICON_COLOR_PAIR_GIT, COLOR_YELLOW, COLOR_BLACK),
+    ("icon_rust", ICON_COLOR_PAIR_RUST, COLOR_YELLOW, COLOR_BLACK),
+    ("icon_config", ICON_COLOR_PAIR_CONFIG, COLOR_CYAN, COLOR_BLACK),
+    ("icon_lock", ICON_COLOR_PAIR_LOCK, COLOR_YELLOW, COLOR_BLACK),
+    ("icon_json", ICON_COLOR_PAIR_JSON, COLOR_YELLOW, COLOR_BLACK),
+    ("icon_js", ICON_COLOR_PAIR_JS, COLOR_GREEN, COLOR_BLACK),
+    ("icon_svg", ICON_COLOR_PAIR_SVG, COLOR_RED, COLOR_BLACK),
+    ("icon_css", ICON_COLOR_PAIR_CSS, COLOR_BLUE, COLOR_BLACK),
+    ("icon_html", ICON_COLOR_PAIR_HTML, COLOR_YELLOW, COLOR_BLACK),
+    ("icon_font", ICON_COLOR_PAIR_FONT, COLOR_RED, COLOR_BLACK),
+];
+
+/// Sets up the color pairs of the file types and icons, the colors of the config replace the default ones
+fn init_colors(colors: &HashMap<String, (i16, Option<i16>)>) {
+    for &(name, pair, fg, bg) in COLOR_ROLES {
+        let (fg, bg): (i16, i16) = match colors.get(name) {
+            Some(&(fg, custom_bg)) => (fg, custom_bg.unwrap_or(bg)),
+            None => (fg, bg),
+        };
+        init_pair(pair, fg, bg);
+    }
+}
+
+/// The attributes of a date in the date column, from bright for the last hour to dim past a month
+fn age_attr(time: SystemTime) -> chtype {
+    let secs: u64 = SystemTime::now().duration_since(time).map(|age| age.as_secs()).unwrap_or(0);
+    match secs {
+        0..3600 => COLOR_PAIR(AGE_COLOR_PAIR_RECENT as chtype) | A_BOLD,
+        3600..86400 => COLOR_PAIR(AGE_COLOR_PAIR_RECENT as chtype),
+        86400..604800 => COLOR_PAIR(AGE_COLOR_PAIR_WEEK as chtype),
+        604800..2592000 => COLOR_PAIR(FILE_COLOR_PAIR_FILE as chtype),
+        _ => A_DIM,
+    }
+}
+
+/// Set once an interrupt (`Ctrl-C`) was received
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn on_interrupt(_signal: libc::c_int) {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+}
+
+/// Refuses destructive actions in read-only mode, telling why on the status line
+fn allow_changes(config: &Config, status: &mut Option<(String, Instant)>) -> bool {
+    if config.read_only {
+        *status = Some(("read-only mode".to_string(), Instant::now()));
+    }
+    !config.read_only
+}
 
-            if i+scroll == selected { win.attron(A_REVERSE); }
-            win.attron(COLOR_PAIR(ft));
-            win.printw(format!("{}",entry.file_name()));
-            win.attroff(COLOR_PAIR(ft));
-            if i+scroll == selected { win.attroff(A_REVERSE); }
+/// Returns whether `entry` can be opened, those bigger than `confirm_open` are only opened once the user agreed to it
+fn open_anyway(win: &Window, config: &Config, entry: &FileStat) -> bool {
+    config.confirm_open == 0 || entry.size() <= config.confirm_open
+        || confirm(win, &format!("{} is {}, open anyway?", entry.file_name(), human_size(entry.size())))
+}
+
+/// Starts deleting `entry` once the user confirmed it, directories are deleted along with their contents
+///
+/// Returns `None` if the user changed their mind
+fn delete_entry(win: &Window, entry: &FileStat) -> Option<Job> {
+    let question: String = if entry.is_dir() {
+        format!("delete {} and everything inside?", entry.file_name())
+    }
+    else {
+        format!("delete {}?", entry.file_name())
+    };
+    if !confirm(win, &question) {
+        return None;
+    }
+    Some(delete_job(vec![PathBuf::from(entry.path())]))
+}
 
-            win.mv(i+1 as i32,25);
-            win.clrtoeol();
+/// How many names the summary of a batch operation lists
+const SUMMARY_NAMES: usize = 8;
 
-            let meta: Metadata = entry.metadata();
-            //format("%d-%m-%Y %H:%M");
-            win.printw(format!(" {}",DateTime::from_timestamp((meta.accessed().unwrap().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64)+(Local::now().offset().local_minus_utc() as i64), 0).unwrap().format("%d-%m-%Y %H:%M")));
+/// Starts deleting the marked `entries` once the user confirmed it after a summary of what goes away
+///
+/// Returns `None` if the user changed their mind
+fn delete_marked(win: &Window, entries: &[&FileStat], size_format: fn(u64) -> String, dir_sizes: &mut DirSizes) -> Option<Job> {
+    let dirs: usize = entries.iter().filter(|f| f.is_dir()).count();
+    let file_size: u64 = entries.iter().filter(|f| !f.is_dir()).map(|f| disk_usage(Path::new(f.path()))).sum();
+    let mut names: Vec<String> = entries.iter().take(SUMMARY_NAMES).map(|f| f.file_name().to_string()).collect();
+    if entries.len() > SUMMARY_NAMES {
+        names.push(format!("and {} more", entries.len() - SUMMARY_NAMES));
+    }
+    // Directories are measured in the background, the total fills in while the question is asked
+    let summary = || {
+        let sizes: Vec<Option<u64>> = entries.iter().filter(|f| f.is_dir()).map(|f| dir_sizes.size(f)).collect();
+        let size: u64 = file_size + sizes.iter().flatten().sum::<u64>();
+        let total: String = if sizes.iter().all(Option::is_some) {
+            format!("{} in total", size_format(size))
         }
+        else {
+            format!("{} so far, computing\u{2026}", size_format(size))
+        };
+        let mut lines: Vec<String> = vec![format!("{} files and {} directories, {}", entries.len() - dirs, dirs, total), String::new()];
+        lines.extend(names.iter().cloned());
+        lines
+    };
+    if !confirm_changing_list(win, &format!("delete {} entries and everything inside?", entries.len()), summary) {
+        return None;
+    }
+    Some(delete_job(entries.iter().map(|f| PathBuf::from(f.path())).collect()))
+}
 
-        if filez.len() > 0 { selected = selected.clamp(0, filez.len() as i32-1); }
+/// Deletes `paths` and everything inside them as a job, big directories take a while
+fn delete_job(paths: Vec<PathBuf>) -> Job {
+    let what: String = match paths.as_slice() {
+        [path] => path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default(),
+        _ => format!("{} entries", paths.len()),
+    };
+    Job::spawn(format!("deleting {}", what), 0, move |progress| {
+        let mut errors: Vec<(PathBuf, io::Error)> = vec![];
+        for path in &paths {
+            // The entry may have gone with a directory deleted before it
+            if fs::symlink_metadata(path).is_err() { continue }
+            errors.extend(ops::remove_all(path, progress));
+        }
+        match errors.as_slice() {
+            _ if progress.is_cancelled() => format!("stopped deleting {}", what),
+            [] => format!("deleted {}", what),
+            [(path, err)] => format!("could not delete {}: {}", path.display(), err),
+            [(path, err), ..] => format!("could not delete {} entries, {}: {}", errors.len(), path.display(), err),
+        }
+    })
+}
 
-        if selected > win.get_max_y()-3+scroll {
-            while selected > win.get_max_y()-3+scroll {scroll += 1;}
+/// Renames `entries` to `targets` once the user agreed to the new names
+///
+/// Returns the entries that couldn't be renamed, or `None` if the user cancelled
+fn rename_marked(win: &Window, entries: &[PathBuf], targets: &[PathBuf]) -> Option<Vec<(String, io::Error)>> {
+    let name = |path: &PathBuf| path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+    let renamed: Vec<(&PathBuf, &PathBuf)> = entries.iter().zip(targets).filter(|(from, to)| from != to).collect();
+    let mut lines: Vec<String> = renamed.iter().take(SUMMARY_NAMES).map(|(from, to)| format!("{} \u{2192} {}", name(from), name(to))).collect();
+    if renamed.len() > SUMMARY_NAMES {
+        lines.push(format!("and {} more", renamed.len() - SUMMARY_NAMES));
+    }
+    if !confirm_list(win, &format!("rename {} entries?", renamed.len()), &lines) {
+        return None;
+    }
+    let mut errors: Vec<(String, io::Error)> = vec![];
+    for (from, to) in renamed {
+        if let Err(err) = ops::rename(from, to) {
+            errors.push((name(from), err));
         }
-        if selected < scroll {
-            while selected < scroll {scroll -= 1;}
+    }
+    Some(errors)
+}
+
+/// Returns whether quitting can go on, running jobs are cancelled once the user agreed to it
+fn quit_anyway(win: &Window, jobs: &mut [Job]) -> bool {
+    if jobs.iter().all(Job::is_done) {
+        return true;
+    }
+    if !confirm(win, "operation in progress, quit anyway?") {
+        return false;
+    }
+    jobs.iter_mut().for_each(Job::cancel);
+    true
+}
+
+/// Returns whether quitting can go on with `marked` entries marked, with `ask` the user has to agree to lose them
+fn quit_marked(win: &Window, marked: usize, ask: bool) -> bool {
+    !ask || marked == 0 || confirm(win, &format!("{} {} marked, quit?", marked, if marked == 1 { "entry" } else { "entries" }))
+}
+
+/// The icon of directories that can't be read
+const LOCKED_ICON: &str = "\u{f023}";
+
+/// How many columns each level of the tree view is indented by
+pub const TREE_INDENT: usize = 2;
+
+/// The width of the icon column, the icon is surrounded by spaces
+const ICON_COLUMN_WIDTH: i32 = 3;
+/// The width of the name column, longer names get cut by the date
+const NAME_COLUMN_WIDTH: i32 = 22;
+
+/// The width of the git status column, including its trailing space
+const GIT_COLUMN_WIDTH: i32 = 3;
+
+/// The width of the extension column, including its leading space
+const EXTENSION_COLUMN_WIDTH: i32 = 7;
+
+/// The width of the date column, including its leading space
+const DATE_COLUMN_WIDTH: i32 = 17;
+/// The width of the date column with short dates, including its leading space
+const SHORT_DATE_COLUMN_WIDTH: i32 = 11;
+/// The width of the size column, including its leading space
+const SIZE_COLUMN_WIDTH: i32 = 14;
+
+/// The width of the owner column, including its leading spaces
+const OWNER_COLUMN_WIDTH: i32 = 20;
+/// The width of the permissions column, including its leading space
+const PERMISSIONS_COLUMN_WIDTH: i32 = 11;
+
+/// Returns the width of a column, dates are `date_width` wide
+fn column_width(column: Column, date_width: i32) -> i32 {
+    match column {
+        Column::GitStatus => GIT_COLUMN_WIDTH,
+        Column::Icon => ICON_COLUMN_WIDTH,
+        Column::Name => NAME_COLUMN_WIDTH,
+        Column::Extension => EXTENSION_COLUMN_WIDTH,
+        Column::Date => date_width,
+        Column::Size => SIZE_COLUMN_WIDTH,
+        Column::Owner => OWNER_COLUMN_WIDTH,
+        Column::Permissions => PERMISSIONS_COLUMN_WIDTH,
+    }
+}
+
+/// Places `columns` one after the other from `x`, returns where each of them starts and where the last one ends
+fn column_layout(columns: &[Column], x: i32, date_width: i32) -> (Vec<(Column, i32)>, i32) {
+    let mut end: i32 = x;
+    let layout: Vec<(Column, i32)> = columns.iter().map(|&column| {
+        let start: i32 = end;
+        end += column_width(column, date_width);
+        (column, start)
+    }).collect();
+    (layout, end)
+}
+
+/// How often the watcher checks whether the directory changed while waiting for the next refresh
+const WATCHER_TICK: Duration = Duration::from_millis(10);
+/// How long navigating waits for the first entries of the target before showing it anyway
+const NAVIGATE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How long the main loop waits before checking again whether something changed
+const IDLE_SLEEP: Duration = Duration::from_millis(10);
+/// How long a frame stays on screen at most without being drawn again
+const IDLE_REDRAW: Duration = Duration::from_secs(1);
+
+/// How long a file is shown as being written to after it last changed between scans
+const CHANGING_HOLD: Duration = Duration::from_millis(1500);
+
+/// How long a status message stays on the last line
+const STATUS_DURATION: Duration = Duration::from_secs(3);
+
+fn main() {
+    let mut config: Config = match Config::load(cmdargs().skip(1)) {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("filez: {}", err);
+            process::exit(1);
         }
+    };
 
-        selected_hist.insert(path.to_str().unwrap().to_string(), View{selected,scroll});
-        
-        win.refresh();
+    // Without a path argument, browsing starts from $FILEZ_HOME if it is set, or the current directory
+    let mut start_warning: Option<String> = None;
+    let start: Option<String> = config.path.clone().or_else(|| {
+        let home: String = env_var("FILEZ_HOME").ok().filter(|home| !home.is_empty())?;
+        if Path::new(&home).is_dir() {
+            return Some(home);
+        }
+        start_warning = Some(format!("FILEZ_HOME ({}) is not a directory, starting in the current one", home));
+        None
+    });
+    // Links are followed to the directory they lead to unless the path is to be shown as it was given
+    let start: PathBuf = start.map(|start| expand_tilde(&start)).unwrap_or_else(|| current_dir().unwrap());
+    let start: PathBuf = if config.resolve_start { normalize_path(start) } else { resolve_dots(&std::path::absolute(&start).unwrap_or(start)) };
 
-        match win.getch() {
-            Some(Input::Character(c)) => {
-                if c == 'q' {
-                    break
-                }
-                if c == '\x08' {
-                    let old_path: PathBuf  = file_watcher.path();
-                    file_watcher.set_path(Box::new(|path: &mut PathBuf|{
-                        path.pop();
-                    }));
-                    while file_watcher.path2().to_str() == old_path.to_str() { /*thread::sleep(Duration::from_millis(100))*/ }
-                    let nview: View = selected_hist.get(&file_watcher.path().to_str().unwrap().to_string()).copied().unwrap_or_else(||{
-                        let mut i: usize = 0;
-                        for f in file_watcher.filez() {
-                            if f.file_name() == old_path.file_name().unwrap() {
-                                return View {
-                                    selected: i as i32,
-                                    scroll: i as i32
-                                };
-                            }
-                            i += 1;
-                        }
-                        View { 
-                            selected: 0,
-                            scroll: 0,
-                        }
-                    });
-                    selected = nview.selected;
-                    scroll = nview.scroll;
-                }
-                if c == '\x0a' {
-                    let f: FileStat = file_watcher.filez()[selected as usize].clone();
-                    if f.is_dir() {
-                        let old_path: PathBuf  = file_watcher.path();
-                        file_watcher.set_path(Box::new(move |path: &mut PathBuf|{
-                            path.push(f.file_name());
-                        }));
-                        while file_watcher.path2().to_str() == old_path.to_str() { }
-                        let nview: View = selected_hist.get(&file_watcher.path().to_str().unwrap().to_string()).copied().unwrap_or_else(||{
-                            let mut i: usize = 0;
-                            for f in file_watcher.filez() {
-                                if f.file_name() == old_path.file_name().unwrap() {
-                                    return View {
-                                        selected: i as i32,
-                                        scroll: i as i32
-                                    };
-                                }
-                                i += 1;
-                            }
-                            View { 
-                                selected: 0,
-                                scroll: 0,
-                            }
-                        });
-                        selected = nview.selected;
-                        scroll = nview.scroll;
-                    }
-                    else {
-                        if consts::OS == "windows" {
-                            Command::new("explorer").arg(f.path()).spawn().unwrap();
-                        }
-                    }
-                }
-            }
-            Some(Input::KeyMouse) => {
-                if let Ok(evt) = getmouse() {
-                    /*
-                    Left:
-                        4    : click
-                        2    : press
-                        1    : release
-                    Right:
-                        4096 : click
-                        2048 : press
-                        1024 : release
-                    Middle:
-                        128  : click
-                        64   : press
-                        32   : release
-                    Scroll:
-                        65536 : up
-                        2097152 : down
-                    */
-                    if evt.bstate & 65536 != 0 { scroll -= 1; }
-                    if evt.bstate & 2097152 != 0 { scroll += 1; }
-                }
-            },
-            Some(Input::KeyDown) => {selected += 1;},
-            Some(Input::KeyUp)   => {selected -= 1;},
-            _ => {}
+    // The listing can be printed for scripts, without starting the interface
+    if config.list {
+        if let Some(warning) = &start_warning {
+            eprintln!("filez: {}", warning);
+        }
+        if let Err(err) = listing::print(&start, &config) {
+            eprintln!("filez: {}: {}", start.display(), err);
+            process::exit(1);
         }
+        return;
+    }
+
+    // Interrupts are turned into a regular quit so that the terminal always gets restored
+    unsafe { libc::signal(libc::SIGINT, on_interrupt as extern "C" fn(libc::c_int) as libc::sighandler_t); }
 
+    // Curses only prints UTF-8 (icons, arrows...) properly with the locale of the user
+    unsafe { libc::setlocale(libc::LC_ALL, c"".as_ptr()); }
+
+    let win: Window = initscr();
+
+    win.keypad(true);
+    win.nodelay(true);
+    // Without the mouse, the terminal can still select text to copy it
+    if config.mouse {
+        mousemask(ALL_MOUSE_EVENTS, std::ptr::null_mut());
     }
+    noecho();
+    curs_set(0);
 
-    endwin();
+    // Terminals without colors get a monochrome rendering
+    let colors: bool = config.color && has_colors();
+    if colors {
+        start_color();
+        init_colors(&config.colors);
+    }
+    // Names are colored like `ls` does when the user has its colors set up
+    let mut ls_colors: Option<LsColors> = if colors && config.ls_colors { LsColors::from_env(LS_COLORS_FIRST_PAIR) } else { None };
 
+    let file_watcher: FileWatcher = FileWatcher::new(start, config.sort);
+    file_watcher.set_hidden(config.hidden);
+    file_watcher.set_ignore(config.ignore.clone());
+    file_watcher.set_dotfiles(config.dotfiles);
+    file_watcher.set_groups(config.group_order);
+    if config.sort_per_dir {
+        file_watcher.remember_sorts();
+    }
+    
+    let mut selected: i32 = 0;
+    let mut selected_hist: HashMap<String,View> = HashMap::new();
+    let mut scroll: i32 = 0;
+    let mut status: Option<(String, Instant)> = start_warning.map(|warning| (warning, Instant::now()));
+    let mut show_info: bool = false;
+    let mut show_preview: bool = false;
+    // The previewed file, drawn again when it changes
+    let mut preview_watch: FileWatch = FileWatch::default();
+    let mut tree: Tree = Tree::new(config.tree, config.tree_depth);
+    let mut show_icons: bool = config.icons;
+    let mut exact_sizes: bool = config.exact_sizes;
+    let mut size_bars: bool = config.size_bars;
+    let mut time_column: TimeColumn = TimeColumn::Accessed;
+    let mut date_format: DateFormat = config.date_format;
+    let mut show_owner: bool = config.owner;
+    let mut show_dir_sizes: bool = config.dir_sizes;
+    let mut dir_sizes: DirSizes = DirSizes::default();
+    // Only built once the user jumps somewhere for the first time
+    let mut dir_index: Option<DirIndex> = None;
+    let mut show_age_colors: bool = config.age_colors;
+    let mut grid: bool = config.grid;
+    let mut show_slashes: bool = config.slashes;
+    // Whether the ignore list is turned off for now
+    let mut show_ignored: bool = false;
+    let mut git_status: git::StatusCache = git::StatusCache::default();
+    let mut show_snippets: bool = config.snippets;
+    let mut show_extensions: bool = config.extensions;
+    let mut show_tilde: bool = config.tilde;
+    // Resolved like browsed paths are, so that it can be recognized in them
+    let home: Option<PathBuf> = home_dir().map(normalize_path);
+    // Where filez was started from, relative paths are copied from there
+    let launch_dir: Option<PathBuf> = current_dir().ok().map(normalize_path);
+    let mut owners: Owners = Owners::default();
+    let mut cd_on_quit: bool = false;
+    let mut search: Option<Search> = None;
+    // The ancestor highlighted in the header while one is being picked with the keyboard
+    let mut crumb: Option<PathBuf> = None;
+    // The directory that going up stops at, until it is unlocked or left some other way
+    let mut root: Option<PathBuf> = None;
+    // Paths of the marked entries of the current directory
+    let mut marked: HashSet<String> = HashSet::new();
+    let mut marked_dir: PathBuf = PathBuf::new();
+    // The last pattern entries were selected with
+    let mut select_pattern: String = String::new();
+    // The path of the selected entry, so that the cursor stays on it when the listing changes
+    let mut tracked_selection: Option<String> = None;
+    // An entry to select once the watcher lists it
+    let mut pending_select: Option<PathBuf> = None;
+    // Visited directories, most recent first
+    let mut visited: Vec<String> = vec![];
+    let mut info_cache: InfoCache = InfoCache::default();
+    // The free space of the filesystem of a directory, only read again when the directory changes
+    let mut free: (PathBuf, Option<u64>) = (PathBuf::new(), None);
+    // Work running in the background, shown in the footer
+    let mut jobs: Vec<Job> = vec![];
+
+    spawn_watcher(file_watcher.clone(), config.max_entries, Duration::from_millis(config.refresh_ms));
+
+    // Whether the frame has to be drawn again, otherwise the loop waits for something to change
+    let mut dirty: bool = true;
+    let mut last_draw: Instant = Instant::now();
+    // An input that came while waiting, handled with the inputs of the next frame
+    let mut pending_input: Option<Input> = None;
+
+    'main: loop {
+
+        if INTERRUPTED.load(Ordering::SeqCst) {
+            // Like in prompts, an interrupt only cancels a search being typed
+            match search.take() {
+                Some(s) if s.editing => {
+                    INTERRUPTED.store(false, Ordering::SeqCst);
+                    selected = s.origin;
+                }
+                _ if crumb.take().is_some() => INTERRUPTED.store(false, Ordering::SeqCst),
+                _ => {
+                    INTERRUPTED.store(false, Ordering::SeqCst);
+                    if quit_anyway(&win, &mut jobs) { break 'main }
+                }
+            }
+            dirty = true;
+        }
+
+        // The same frame isn't drawn again, though it is from time to time for what changes on its own (folders of the tree, free space...)
+        if !dirty {
+            thread::sleep(IDLE_SLEEP);
+            pending_input = win.getch();
+            dirty = pending_input.is_some()
+                || file_watcher.take_changed()
+                || dir_sizes.take_changed()
+                || preview_watch.take_changed()
+                // The footer shows their progress
+                || !jobs.is_empty()
+                || status.as_ref().is_some_and(|(_, since)| since.elapsed() >= STATUS_DURATION)
+                || last_draw.elapsed() >= IDLE_REDRAW;
+            if !dirty { continue }
+        }
+        file_watcher.take_changed();
+        last_draw = Instant::now();
+
+        if let Some(notice) = file_watcher.take_notice() {
+            status = Some((notice, Instant::now()));
+            let nview: View = selected_hist.get(file_watcher.path().to_str().unwrap()).copied().unwrap_or(View { selected: 0, scroll: 0 });
+            selected = nview.selected;
+            scroll = nview.scroll;
+        }
+
+        let path: PathBuf = file_watcher.path();
+        // The rows the selection indexes, and how deep each entry is in the tree view
+        let (filez, depths): (Vec<FileStat>, Vec<usize>) = shown_entries(&file_watcher, &mut tree);
+
+        if search.as_ref().is_some_and(|s| s.dir != path) {
+            search = None;
+        }
+        // Rescans, sorting and filters can move the selected entry around
+        if let Some(tracked) = &tracked_selection {
+            if filez.get(selected as usize).map(FileStat::path) != Some(tracked) {
+                if let Some(i) = filez.iter().position(|f| f.path() == tracked) {
+                    selected = i as i32;
+                }
+            }
+        }
+        if marked_dir != path {
+            marked.clear();
+            marked_dir = path.clone();
+        }
+        // Navigating never leaves the locked folder, only its removal moves the listing above it
+        if let Some(removed) = root.take_if(|root| !path.starts_with(root)) {
+            status = Some((format!("unlocked {}, it no longer exists", removed.display()), Instant::now()));
+        }
+        if let Some(target) = &pending_select {
+            if !target.starts_with(&path) {
+                pending_select = None;
+            }
+            else if let Some(i) = filez.iter().position(|f| Path::new(f.path()) == target) {
+                selected = i as i32;
+                pending_select = None;
+            }
+        }
+
+        // Keeps the selection in view before drawing, it may just have been found again somewhere else
+        if !filez.is_empty() { selected = selected.clamp(0, filez.len() as i32-1); }
+
+        // Looked up every frame, the terminal may have been resized
+        let rows: i32 = body_rows(win.get_max_y(), config.rows);
+
+        win.clear();
+
+        // The separators are dimmed so that the clickable breadcrumb segments stand out
+        win.mv(0, 0);
+        // Dropped if the directory changed under it
+        if crumb.as_ref().is_some_and(|crumb| !path.starts_with(crumb)) {
+            crumb = None;
+        }
+        // The home directory becomes `~`, unless an ancestor above it is being picked
+        let tilde_home: Option<&Path> = home.as_deref()
+            .filter(|home| show_tilde && path.starts_with(home) && crumb.as_ref().is_none_or(|crumb| crumb.starts_with(home)));
+        let header: String = match tilde_home {
+            Some(home) => tildify(&path, home),
+            None if is_drive_list(&path) => "Drives".to_string(),
+            None => path.to_str().unwrap().to_string(),
+        };
+        // How many characters of the path the `~` stands for
+        let header_offset: usize = path.to_string_lossy().chars().count().saturating_sub(header.chars().count());
+        let crumb_span: std::ops::Range<usize> = crumb.as_deref().map(breadcrumb_span)
+            .map(|span| span.start.saturating_sub(header_offset)..span.end.saturating_sub(header_offset))
+            .unwrap_or(0..0);
+        for (i, c) in header.chars().enumerate() {
+            if crumb_span.contains(&i) {
+                win.attron(A_REVERSE);
+                win.addstr(c.to_string());
+                win.attroff(A_REVERSE);
+            }
+            else if is_separator(c) {
+                win.attron(A_DIM);
+                win.addstr(c.to_string());
+                win.attroff(A_DIM);
+            }
+            else {
+                win.addstr(c.to_string());
+            }
+        }
+
+        if free.0 != path {
+            free = (path.clone(), free_space(&path));
+        }
+        if let Some(bytes) = free.1 {
+            let text: String = format!("{} free", human_size(bytes));
+            let x: i32 = win.get_max_x() - text.chars().count() as i32;
+            // The path has priority
+            if x > header.chars().count() as i32 + 1 {
+                win.attron(A_DIM);
+                win.mvaddstr(0, x, text);
+                win.attroff(A_DIM);
+            }
+        }
+
+        if config.git_status {
+            git_status.update(&path, filez.len(), filez.iter().filter_map(FileStat::modified).max());
+        }
+        let size_format: fn(u64) -> String = if exact_sizes { grouped_bytes } else { human_size };
+        // The columns come in the order of the config, those that are turned off take no room
+        let shown_columns: Vec<Column> = config.columns.iter().copied()
+            .filter(|column| match column {
+                Column::GitStatus => config.git_status && git_status.in_repo(),
+                Column::Icon => show_icons,
+                Column::Extension => show_extensions,
+                Column::Owner => show_owner,
+                _ => true,
+            })
+            .collect();
+        // The `>` of the selection goes where the icon column has room for it, in a column of its own otherwise
+        let gutter: i32 = if config.highlight == Highlight::Gutter && !shown_columns.contains(&Column::Icon) { 1 } else { 0 };
+        let split_extensions: bool = shown_columns.contains(&Column::Extension);
+        // Automatic dates are shortened when the dates or sizes would be cut off otherwise
+        let short_dates: bool = match date_format {
+            DateFormat::Full => false,
+            DateFormat::Short => true,
+            DateFormat::Auto => column_layout(&shown_columns, gutter, DATE_COLUMN_WIDTH).0.iter()
+                .filter(|(column, _)| matches!(column, Column::Date | Column::Size))
+                .any(|&(column, x)| x + column_width(column, DATE_COLUMN_WIDTH) > win.get_max_x()),
+        };
+        let (date_width, date_text): (i32, fn(SystemTime) -> String) = if short_dates {
+            (SHORT_DATE_COLUMN_WIDTH, format_short_time)
+        }
+        else {
+            (DATE_COLUMN_WIDTH, format_time)
+        };
+        let (layout, link_x): (Vec<(Column, i32)>, i32) = column_layout(&shown_columns, gutter, date_width);
+        // Where names start, the grid only has the columns that come before them
+        let name_x: i32 = layout.iter().find(|(column, _)| *column == Column::Name).map_or(gutter, |&(_, x)| x);
+        // The grid fills columns from top to bottom, as wide as the longest name so that they all fit
+        let cell_width: i32 = if grid {
+            let longest: usize = filez.iter().map(|f| f.file_name().chars().count() + (show_slashes && f.is_dir()) as usize).max().unwrap_or(0);
+            (name_x + longest as i32 + 2).min(win.get_max_x()).max(1)
+        }
+        else {
+            win.get_max_x().max(1)
+        };
+        let columns: i32 = if grid { (win.get_max_x() / cell_width).max(1) } else { 1 };
+        // The grid scrolls a whole column at a time
+        if grid {
+            let rows: i32 = rows.max(1);
+            if selected < scroll { scroll = selected / rows * rows; }
+            if selected >= scroll + rows*columns { scroll = (selected / rows - columns + 1) * rows; }
+            scroll = scroll.max(0) / rows * rows;
+        }
+        else {
+            if selected > rows-1+scroll {
+                while selected > rows-1+scroll {scroll += 1;}
+            }
+            if selected < scroll {
+                while selected < scroll {scroll -= 1;}
+            }
+        }
+        let changing: HashSet<String> = file_watcher.changing();
+        // Bars are as long as the largest size allows, folders count once their size is known
+        let largest: u64 = if size_bars && show_dir_sizes {
+            filez.iter().filter(|f| f.is_dir()).filter_map(|f| dir_sizes.size(f)).fold(file_watcher.largest(), u64::max)
+        }
+        else {
+            file_watcher.largest()
+        };
+        for i in 0i32..rows*columns {
+            if i+scroll < 0 {continue}
+            if i+scroll >= filez.len() as i32 {break}
+            let entry: &FileStat = &filez[(i+scroll) as usize];
+            // Where the entry goes, its row is `y` and its cell starts at `x`
+            let (y, x): (i32, i32) = if grid { (i % rows + 1, i / rows * cell_width) } else { (i+1, 0) };
+
+            let is_selected: bool = i+scroll == selected;
+            win.mv(y,x);
+            win.printw(" ".repeat(gutter as usize));
+            let depth: usize = depths.get((i+scroll) as usize).copied().unwrap_or(0);
+            // The tree view indents the icon and the name, the name gives up room for it
+            let indent: i32 = if grid { 0 } else { (depth * TREE_INDENT) as i32 };
+
+            let unreadable: bool = entry.is_dir() && !info_cache.readable(entry);
+
+            // Without colors, directories are told apart by being bold
+            let ft: chtype = if !colors {
+                if entry.is_dir() { A_BOLD } else { 0 }
+            }
+            else if let Some(attr) = ls_colors.as_ref().and_then(|ls_colors| ls_colors.attr(entry)) {
+                attr
+            }
+            else if entry.is_dir() {
+                COLOR_PAIR(FILE_COLOR_PAIR_DIR as chtype)
+            }
+            else if entry.is_file() {
+                COLOR_PAIR(FILE_COLOR_PAIR_FILE as chtype)
+            }
+            else {
+                COLOR_PAIR(FILE_COLOR_PAIR_EXTRA as chtype)
+            };
+            let match_attr: chtype = if colors { COLOR_PAIR(FILE_COLOR_PAIR_EXTRA as chtype) | A_BOLD } else { A_UNDERLINE };
+            // Marked entries look like matches
+            let ft: chtype = if marked.contains(entry.path()) { match_attr } else { ft };
+            let ft: chtype = if unreadable { ft | A_DIM } else { ft };
+
+            // The extension of files moves to its own column
+            let (name, extension): (&str, &str) = if split_extensions && entry.is_file() && !grid { split_extension(entry.file_name()) } else { (entry.file_name(), "") };
+            // Names are cut to their cell in the grid, the terminal being narrower than them
+            let name: &str = match name.char_indices().nth((cell_width - name_x - 1).max(1) as usize).filter(|_| grid) {
+                Some((end, _)) => &name[..end],
+                None => name,
+            };
+
+            for &(column, column_x) in &layout {
+                let column_x: i32 = x + column_x + if matches!(column, Column::Icon | Column::Name) { indent } else { 0 };
+                // Columns that would be cut off are left out, rather than wrapping to the next row
+                if column != Column::Name && column_x + column_width(column, date_width) > win.get_max_x() { break }
+                win.mv(y,column_x);
+                // Whatever the column before ran into is cut, cells of the grid are as wide as they need
+                if !grid { win.clrtoeol(); }
+                match column {
+                    Column::GitStatus => {
+                        let mark: &str = git_status.mark(Path::new(entry.path())).unwrap_or("");
+                        let mark_attr: chtype = match mark {
+                            _ if !colors => A_BOLD,
+                            "??" => A_DIM,
+                            mark if mark.contains('D') || mark.contains('U') => COLOR_PAIR(FILE_COLOR_PAIR_BROKEN_LINK as chtype),
+                            mark if mark.contains('A') => COLOR_PAIR(FILE_COLOR_PAIR_LINK as chtype),
+                            _ => COLOR_PAIR(FILE_COLOR_PAIR_EXTRA as chtype),
+                        };
+                        win.attron(mark_attr);
+                        win.printw(format!("{:>1$} ", mark, (GIT_COLUMN_WIDTH-1) as usize));
+                        win.attroff(mark_attr);
+                    }
+                    Column::Icon => {
+                        win.printw(" ");
+                        let mut found: bool = false;
+                        let file_name =  entry.file_name();
+                        if unreadable {
+                            if colors { win.attron(COLOR_PAIR(ICON_COLOR_PAIR_LOCK as chtype)); }
+                            win.printw(LOCKED_ICON);
+                            if colors { win.attroff(COLOR_PAIR(ICON_COLOR_PAIR_LOCK as chtype)); }
+                            found = true;
+                        }
+                        // Icons of the config come before the built-in ones
+                        let custom_icon: Option<&String> = file_name.rsplit_once('.')
+                            .filter(|_| !found && entry.is_file())
+                            .and_then(|(_, ext)| config.extension_icons.get(&ext.to_lowercase()));
+                        if let Some(icon) = custom_icon {
+                            win.printw(icon);
+                            found = true;
+                        }
+                        for icon in ICONS.iter().filter(|_| !found) {
+                            if (icon.m)(file_name,entry.to_owned()) {
+                                if colors { win.attron(COLOR_PAIR(icon.color as chtype)); }
+                                win.printw(icon.icon);
+                                if colors { win.attroff(COLOR_PAIR(icon.color as chtype)); }
+                                found = true;
+                                break;
+                            }
+                        }
+                        if !found { win.printw("?"); }
+                        win.printw(" ");
+                    }
+                    Column::Name => {
+                        let reverse: bool = is_selected && config.highlight == Highlight::Reverse;
+                        if reverse { win.attron(A_REVERSE); }
+                        win.attron(ft);
+                        // The part of the name matching the search stands out
+                        match search.as_ref().and_then(|s| s.find(entry.file_name())) {
+                            Some(range) => {
+                                let range: std::ops::Range<usize> = range.start.min(name.len())..range.end.min(name.len());
+                                win.printw(&name[..range.start]);
+                                win.attroff(ft);
+                                win.attron(match_attr);
+                                win.printw(&name[range.clone()]);
+                                win.attroff(match_attr);
+                                win.attron(ft);
+                                win.printw(&name[range.end..]);
+                            }
+                            None => { win.printw(name); }
+                        }
+                        if show_slashes && entry.is_dir() { win.printw(MAIN_SEPARATOR_STR); }
+                        win.attroff(ft);
+                        if reverse { win.attroff(A_REVERSE); }
+                        // The grid only has the columns up to the name, the details are left to the info panel
+                        if grid { break }
+                    }
+                    Column::Extension => {
+                        let width: usize = (EXTENSION_COLUMN_WIDTH-1) as usize;
+                        win.attron(A_DIM);
+                        win.printw(format!(" {:>1$}", truncate_start(extension, width), width));
+                        win.attroff(A_DIM);
+                    }
+                    // The entry may have disappeared since it was listed
+                    Column::Date => if let Some(time) = entry.metadata().and_then(|meta| time_column.get(&meta)) {
+                        let attr: chtype = if colors && show_age_colors { age_attr(time) } else { A_NORMAL };
+                        win.printw(" ");
+                        win.attron(attr);
+                        win.printw(date_text(time));
+                        win.attroff(attr);
+                    }
+                    Column::Size => {
+                        let width: usize = (SIZE_COLUMN_WIDTH-1) as usize;
+                        // Directories show the total size of their files once it is known, or a dash, bars leave them empty until then
+                        let size: Option<String> = if entry.is_file() {
+                            Some(if size_bars { size_bar(entry.size(), largest, width) } else { size_format(entry.size()) })
+                        }
+                        else if entry.is_dir() && !unreadable {
+                            Some(match show_dir_sizes.then(|| dir_sizes.size(entry)).flatten() {
+                                Some(size) if size_bars => size_bar(size, largest, width),
+                                Some(size) => size_format(size),
+                                None if size_bars => String::new(),
+                                None if show_dir_sizes => "\u{2026}".to_string(),
+                                None => "-".to_string(),
+                            })
+                        }
+                        else {
+                            None
+                        };
+                        if let Some(size) = size {
+                            if size_bars {
+                                win.attron(A_DIM);
+                                win.printw(format!(" {:<1$}", size, width));
+                                win.attroff(A_DIM);
+                            }
+                            else {
+                                win.printw(format!(" {:>1$}", size, width));
+                            }
+                            // Files being written to get a spinner in front of their growing size
+                            if changing.contains(entry.path()) {
+                                let frame: usize = (SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default().as_millis() / SPINNER_FRAME_MS) as usize;
+                                let spinner_x: i32 = if size_bars { x } else { (x + SIZE_COLUMN_WIDTH - size.chars().count() as i32 - 2).max(x) };
+                                if colors { win.attron(COLOR_PAIR(FILE_COLOR_PAIR_EXTRA as chtype)); }
+                                win.mvaddstr(y, spinner_x, SPINNER[frame % SPINNER.len()].to_string());
+                                if colors { win.attroff(COLOR_PAIR(FILE_COLOR_PAIR_EXTRA as chtype)); }
+                            }
+                        }
+                    }
+                    Column::Owner => if let Some(owner) = entry.metadata().and_then(|meta| owners.owner(&meta)) {
+                        win.printw(format!("  {}", owner.chars().take((OWNER_COLUMN_WIDTH-2) as usize).collect::<String>()));
+                    }
+                    Column::Permissions => if let Some(meta) = entry.metadata() {
+                        win.printw(format!(" {}", permissions(&meta)));
+                    }
+                }
+            }
+
+            if grid {
+                match config.highlight {
+                    Highlight::Bar if is_selected => {
+                        let (attr, pair): (chtype, i16) = if colors { (A_NORMAL, SELECTION_COLOR_PAIR) } else { (A_REVERSE, 0) };
+                        win.mvchgat(y, x, cell_width-1, attr, pair);
+                    }
+                    Highlight::Gutter if is_selected => {
+                        win.attron(A_BOLD);
+                        win.mvaddstr(y, x, ">");
+                        win.attroff(A_BOLD);
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+
+            if let Some(target) = entry.link().filter(|_| config.link_targets) {
+                // Links that lead nowhere are in red, the others can be followed by clicking their target
+                let broken: bool = !Path::new(entry.path()).exists();
+                let link_attr: chtype = match (colors, broken) {
+                    (true, false) => COLOR_PAIR(FILE_COLOR_PAIR_LINK as chtype),
+                    (true, true) => COLOR_PAIR(FILE_COLOR_PAIR_BROKEN_LINK as chtype),
+                    (false, false) => 0,
+                    (false, true) => A_DIM,
+                };
+                // A name coming last is cut by the target
+                win.mv(y,link_x);
+                win.clrtoeol();
+                win.printw(" -> ");
+                win.attron(link_attr);
+                win.printw(target.to_string_lossy());
+                win.attroff(link_attr);
+            }
+            else if let Some(snippet) = Some(entry).filter(|_| show_snippets).and_then(|entry| info_cache.snippet(entry)) {
+                let width: usize = (win.get_max_x() - link_x - 2).max(0) as usize;
+                let snippet: String = if snippet.chars().count() > width {
+                    snippet.chars().take(width.saturating_sub(1)).chain(std::iter::once('\u{2026}')).collect()
+                }
+                else {
+                    snippet.to_string()
+                };
+                win.mv(y,link_x);
+                win.clrtoeol();
+                win.attron(A_DIM);
+                win.mvaddstr(y, link_x+2, snippet);
+                win.attroff(A_DIM);
+            }
+
+            match config.highlight {
+                Highlight::Bar if is_selected => {
+                    let (attr, pair): (chtype, i16) = if colors { (A_NORMAL, SELECTION_COLOR_PAIR) } else { (A_REVERSE, 0) };
+                    win.mvchgat(y, 0, -1, attr, pair);
+                }
+                Highlight::Gutter if is_selected => {
+                    win.attron(A_BOLD);
+                    win.mvaddstr(y, 0, ">");
+                    win.attroff(A_BOLD);
+                }
+                _ => {}
+            }
+        }
+
+        if filez.is_empty() && file_watcher.path2().as_ref() == Some(&path) {
+            let hint: String = match fs::read_dir(&path) {
+                Err(err) => format!("cannot read this folder: {} \u{2014} press \u{232b} to go back", err),
+                Ok(_) => "empty \u{2014} press \u{232b} to go back".to_string(),
+            };
+            win.attron(A_DIM);
+            win.mvaddstr(1, 1, hint);
+            win.attroff(A_DIM);
+        }
+
+        preview_watch.watch(filez.get(selected as usize).filter(|entry| show_preview && entry.is_file()).map(FileStat::path));
+        match filez.get(selected as usize) {
+            Some(entry) if show_preview && entry.is_file() => draw_preview_panel(&win, rows, entry, &mut info_cache),
+            Some(entry) if show_info => draw_info_panel(&win, rows, entry, &mut info_cache, size_format),
+            _ => {}
+        }
+
+        if status.as_ref().is_some_and(|(_, since)| since.elapsed() >= STATUS_DURATION) {
+            status = None;
+        }
+        // Jobs are only left out once their message was taken
+        jobs.retain_mut(|job| match job.finish() {
+            Some(message) => {
+                status = Some((message, Instant::now()));
+                false
+            }
+            None => true,
+        });
+        let mut footer_fields: Vec<String> = vec![format!("sort: {} {}", file_watcher.sort().name(), if file_watcher.reverse() { "\u{2191}" } else { "\u{2193}" })];
+        if let Some((name, modified)) = file_watcher.newest().filter(|_| config.newest) {
+            footer_fields.push(format!("newest: {} {}", name, format_age(modified)));
+        }
+        if let Some(root) = &root {
+            footer_fields.push(format!("locked: {}", root.file_name().unwrap_or(root.as_os_str()).to_string_lossy()));
+        }
+        footer_fields.extend(jobs.iter().map(Job::describe));
+        let total: usize = file_watcher.total();
+        if total > filez.len() {
+            footer_fields.push(format!("showing {} of {} (truncated)", filez.len(), total));
+        }
+        // Marks of entries that disappeared don't count
+        let (marked_count, marked_size): (usize, u64) = filez.iter()
+            .filter(|f| marked.contains(f.path()))
+            .fold((0, 0), |(count, size), f| (count+1, size + if f.is_file() { f.size() } else { 0 }));
+        if marked_count > 0 {
+            footer_fields.push(format!("{} selected, {}", marked_count, size_format(marked_size)));
+        }
+        if !filez.is_empty() {
+            footer_fields.push(format!("{}/{}", selected+1, filez.len()));
+        }
+        let search_prompt: Option<String> = search.as_ref().map(|s| format!("{}/{}", if s.case_sensitive { "(match case) " } else { "" }, s.query));
+        // Which match the cursor is on out of how many there are, like editors do
+        let search_line: Option<String> = search.as_ref().zip(search_prompt.as_ref()).map(|(s, prompt)| {
+            let matches: Vec<usize> = s.matches(&filez);
+            match matches.iter().position(|&i| i == selected as usize) {
+                _ if s.query.is_empty() => prompt.clone(),
+                _ if matches.is_empty() => format!("{}  [no matches]", prompt),
+                Some(i) => format!("{}  [{}/{}]", prompt, i + 1, matches.len()),
+                None => format!("{}  [-/{}]", prompt, matches.len()),
+            }
+        });
+        let footer_left: &str = match (&status, filez.get(selected as usize)) {
+            _ if search.as_ref().is_some_and(|s| s.editing) => search_line.as_deref().unwrap(),
+            _ if crumb.is_some() => "\u{2190}/\u{2192} pick a folder, Enter to go there, Esc to cancel",
+            (Some((msg, _)), _) => msg,
+            _ if search_line.is_some() => search_line.as_deref().unwrap(),
+            (None, Some(entry)) => entry.path(),
+            (None, None) => "",
+        };
+        draw_footer(&win, footer_left, &footer_fields);
+
+        selected_hist.insert(path.to_str().unwrap().to_string(), View{selected,scroll});
+        if visited.first().map(String::as_str) != path.to_str() {
+            visited.retain(|dir| Some(dir.as_str()) != path.to_str());
+            visited.insert(0, path.to_str().unwrap().to_string());
+        }
+        
+        // The cursor shows where the search is being typed
+        if let Some(line) = search_prompt.as_ref().filter(|_| search.as_ref().is_some_and(|s| s.editing)) {
+            curs_set(1);
+            win.mv(win.get_max_y()-1, line.chars().count().min(win.get_max_x().max(1) as usize - 1) as i32);
+        }
+        else {
+            curs_set(0);
+        }
+
+        win.refresh();
+        dirty = false;
+
+        // Drains every pending input so that held keys don't lag behind, cursor movements are summed up and applied once
+        let mut movement: i32 = 0;
+        for _ in 0..config.input_budget {
+            let input: Input = match pending_input.take().or_else(|| win.getch()) {
+                Some(input) => input,
+                None => break,
+            };
+            dirty = true;
+            match input {
+                Input::KeyDown => { movement += 1; continue; }
+                Input::KeyUp   => { movement -= 1; continue; }
+                _ => {}
+            }
+            // Other inputs may rely on the cursor, so it has to be up to date
+            if movement != 0 {
+                selected = move_selection(selected, movement, filez.len(), config.wrap_cursor);
+                movement = 0;
+            }
+            // While a search is typed, inputs edit it and the cursor follows the first match
+            if let Some(s) = search.as_mut().filter(|s| s.editing) {
+                match input {
+                    Input::Character('\x1b') | Input::Character('\x03') => {
+                        selected = s.origin;
+                        search = None;
+                    }
+                    Input::Character('\n') | Input::KeyEnter => s.editing = false,
+                    Input::Character('\t') => s.case_sensitive = !s.case_sensitive,
+                    Input::KeyBackspace | Input::Character('\x08') | Input::Character('\x7f') => { s.query.pop(); }
+                    Input::Character(c) if !c.is_control() => {
+                        s.query.push(c);
+                        // Once typing leaves a single match and it is a directory, the search goes on inside it
+                        let mut matches = filez.iter().filter(|f| s.find(f.file_name()).is_some());
+                        if let (true, Some(dir), None) = (config.search_enter, matches.next(), matches.next()) {
+                            // Directories that Enter would refuse are left to it, so that it says why
+                            if dir.is_dir() && fs::read_dir(dir.path()).is_ok() && (config.enter_empty || !is_empty_dir(dir.path())) {
+                                let case_sensitive: bool = s.case_sensitive;
+                                match navigate(&file_watcher, &selected_hist, root.as_deref(), PathBuf::from(dir.path())) {
+                                    Ok(nview) => {
+                                        selected = nview.selected;
+                                        scroll = nview.scroll;
+                                        let mut next: Search = Search::new(file_watcher.path(), selected);
+                                        next.case_sensitive = case_sensitive;
+                                        search = Some(next);
+                                        break;
+                                    }
+                                    Err(locked) => status = Some((locked, Instant::now())),
+                                }
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+                if let Some(i) = search.as_ref().and_then(|s| s.first_match(&filez)) {
+                    selected = i as i32;
+                }
+                continue;
+            }
+            // While an ancestor is picked, Left/Right move along the header
+            if let Some(target) = crumb.take() {
+                match input {
+                    Input::KeyLeft | Input::KeyBackspace | Input::Character('\x08') | Input::Character('\x7f') => {
+                        crumb = Some(target.parent().map(Path::to_path_buf).unwrap_or(target));
+                    }
+                    Input::KeyRight => {
+                        let child: Option<&Path> = path.ancestors().take_while(|ancestor| *ancestor != target).last();
+                        crumb = Some(child.map(Path::to_path_buf).unwrap_or(target));
+                    }
+                    Input::Character('\n') | Input::KeyEnter => {
+                        match navigate(&file_watcher, &selected_hist, root.as_deref(), target) {
+                            Ok(nview) => {
+                                selected = nview.selected;
+                                scroll = nview.scroll;
+                                break;
+                            }
+                            Err(locked) => status = Some((locked, Instant::now())),
+                        }
+                    }
+                    Input::Character('\x1b') | Input::Character('q') | Input::Character('\x03') => {}
+                    _ => crumb = Some(target),
+                }
+                continue;
+            }
+            let input: Input = match input {
+                Input::KeyDC => Input::Character('d'),
+                // Every way of going up shares the same handling
+                Input::KeyBackspace | Input::KeyLeft | Input::Character('\x7f') => Input::Character('\x08'),
+                input => input,
+            };
+            match input {
+                Input::Character(c) => {
+                    // Ctrl-C comes as a character when the terminal doesn't turn it into a signal
+                    // Ctrl-C doesn't care about marks, it is the way out when in doubt
+                    if (c == '\x03' || c == 'q' && quit_marked(&win, marked.len(), config.confirm_quit)) && quit_anyway(&win, &mut jobs) {
+                        break 'main
+                    }
+                    if c == 'Q' && quit_marked(&win, marked.len(), config.confirm_quit) && quit_anyway(&win, &mut jobs) {
+                        cd_on_quit = true;
+                        break 'main
+                    }
+                    if c == 'B' {
+                        // Starts on the parent, the current directory is where the user already is
+                        crumb = Some(parent_of(&path));
+                    }
+                    if c == ' ' {
+                        if let Some(f) = filez.get(selected as usize) {
+                            if !marked.remove(f.path()) {
+                                marked.insert(f.path().to_string());
+                            }
+                            selected = move_selection(selected, 1, filez.len(), false);
+                        }
+                    }
+                    if c == '*' {
+                        if let Some(text) = prompt(&win, "select matching: ", &select_pattern).filter(|text| !text.is_empty()) {
+                            status = match NamePattern::new(&text) {
+                                Ok(pattern) => {
+                                    let matching: Vec<&FileStat> = filez.iter().filter(|f| pattern.is_match(f.file_name())).collect();
+                                    marked.extend(matching.iter().map(|f| f.path().to_string()));
+                                    select_pattern = text;
+                                    if matching.is_empty() { Some(("nothing matches".to_string(), Instant::now())) }
+                                    else { Some((format!("selected {} matching entries", matching.len()), Instant::now())) }
+                                }
+                                Err(err) => Some((format!("invalid pattern: {}", err), Instant::now())),
+                            };
+                        }
+                    }
+                    if c == '=' {
+                        let pair: Vec<&FileStat> = filez.iter().filter(|f| marked.contains(f.path())).collect();
+                        if let [a, b] = pair[..] {
+                            if let Err(err) = open::diff(&win, a.path(), b.path()) {
+                                status = Some((format!("could not compare {} and {}: {}", a.file_name(), b.file_name(), err), Instant::now()));
+                            }
+                        }
+                        else {
+                            status = Some((format!("mark two entries to compare them ({} marked)", pair.len()), Instant::now()));
+                        }
+                    }
+                    if c == '/' {
+                        search = Some(Search::new(path.clone(), selected));
+                    }
+                    // While the matches of a search are highlighted, n/N go from one to the next
+                    if let Some(s) = search.as_ref().filter(|_| c == 'n' || c == 'N') {
+                        match s.next_match(&filez, selected.max(0) as usize, c == 'N') {
+                            Some(i) => selected = i as i32,
+                            None => status = Some((format!("no match for {}", s.query), Instant::now())),
+                        }
+                    }
+                    if c == '\x1b' {
+                        search = None;
+                    }
+                    if c == 's' {
+                        file_watcher.set_sort(file_watcher.sort().next());
+                    }
+                    if c == 'H' {
+                        if visited.len() < 2 {
+                            status = Some(("no other directory visited yet".to_string(), Instant::now()));
+                        }
+                        else if let Some(i) = pick(&win, "recent directories", &visited[1..]) {
+                            match navigate(&file_watcher, &selected_hist, root.as_deref(), PathBuf::from(&visited[i+1])) {
+                                Ok(nview) => {
+                                    selected = nview.selected;
+                                    scroll = nview.scroll;
+                                    break;
+                                }
+                                Err(locked) => status = Some((locked, Instant::now())),
+                            }
+                        }
+                    }
+                    if c == 'J' {
+                        // A relative root is taken from where filez was started, like the path argument
+                        let jump_root: Option<PathBuf> = match &config.jump_root {
+                            Some(root) => Some(resolve_dots(&launch_dir.as_deref().unwrap_or(Path::new("")).join(root))),
+                            None => home.clone(),
+                        };
+                        match jump_root {
+                            Some(jump_root) => {
+                                // The index is started over when the root changed with the config
+                                let index: &mut DirIndex = match &mut dir_index {
+                                    Some(index) if index.root() == jump_root => index,
+                                    _ => dir_index.insert(DirIndex::new(jump_root)),
+                                };
+                                index.refresh();
+                                if let Some(target) = fuzzy_pick(&win, index) {
+                                    if !target.is_dir() {
+                                        status = Some((format!("{} is gone", target.display()), Instant::now()));
+                                        continue;
+                                    }
+                                    match navigate(&file_watcher, &selected_hist, root.as_deref(), target) {
+                                        Ok(nview) => {
+                                            selected = nview.selected;
+                                            scroll = nview.scroll;
+                                            break;
+                                        }
+                                        Err(locked) => status = Some((locked, Instant::now())),
+                                    }
+                                }
+                            }
+                            None => status = Some(("no home folder to jump around, see --jump-root".to_string(), Instant::now())),
+                        }
+                    }
+                    if c == 't' {
+                        time_column = time_column.next();
+                        status = Some((format!("showing {} times", time_column.name()), Instant::now()));
+                    }
+                    if c == 'h' {
+                        if let Some(f) = filez.get(selected as usize) {
+                            match git::log(Path::new(f.path())) {
+                                Ok(commits) if commits.is_empty() => status = Some((format!("no commits touch {} yet", f.file_name()), Instant::now())),
+                                Ok(commits) => { pick(&win, &format!("history of {}", f.file_name()), &commits); }
+                                Err(err) => status = Some((err, Instant::now())),
+                            }
+                        }
+                    }
+                    if c == 'T' {
+                        date_format = date_format.next();
+                        status = Some((format!("{} dates", date_format.name()), Instant::now()));
+                    }
+                    if c == '.' {
+                        let hidden: bool = !file_watcher.hidden();
+                        // A hidden entry can't stay selected, the closest entry that remains is selected instead
+                        if !hidden {
+                            let start: usize = (selected.max(0) as usize).min(filez.len());
+                            if let Some(i) = (start..filez.len()).chain((0..start).rev()).find(|&i| !is_hidden(filez[i].file_name())) {
+                                selected = i as i32;
+                            }
+                        }
+                        file_watcher.set_hidden(hidden);
+                        status = Some((if hidden { "showing hidden files" } else { "hiding hidden files" }.to_string(), Instant::now()));
+                        break;
+                    }
+                    if c == 'F' {
+                        if config.ignore.is_empty() {
+                            status = Some(("nothing is ignored, see `ignore` in the config".to_string(), Instant::now()));
+                        }
+                        else {
+                            show_ignored = !show_ignored;
+                            file_watcher.set_ignore(if show_ignored { vec![] } else { config.ignore.clone() });
+                            status = Some((if show_ignored { "showing ignored entries" } else { "hiding ignored entries" }.to_string(), Instant::now()));
+                        }
+                    }
+                    if c == '>' {
+                        let dotfiles: Dotfiles = file_watcher.dotfiles().next();
+                        file_watcher.set_dotfiles(dotfiles);
+                        status = Some((format!("dotfiles: {}", dotfiles.name()), Instant::now()));
+                    }
+                    // `;` only reads the file again, for when it is edited elsewhere
+                    if c == ',' || c == ';' {
+                        let edited: Result<(), String> = if c == ';' { Ok(()) } else {
+                            Config::create_file()
+                                .and_then(|file| open::open(&win, &open::editor(), &file.to_string_lossy()))
+                                .map_err(|err| format!("could not edit the config: {}", err))
+                        };
+                        // The command line still has the last word
+                        match edited.and_then(|_| Config::load(cmdargs().skip(1))) {
+                            Ok(new) => {
+                                // Only what changed in the file is applied, toggles of the session stay as they are otherwise
+                                if new.icons != config.icons { show_icons = new.icons; }
+                                if new.exact_sizes != config.exact_sizes { exact_sizes = new.exact_sizes; }
+                                if new.size_bars != config.size_bars { size_bars = new.size_bars; }
+                                if new.owner != config.owner { show_owner = new.owner; }
+                                if new.dir_sizes != config.dir_sizes { show_dir_sizes = new.dir_sizes; }
+                                if new.date_format != config.date_format { date_format = new.date_format; }
+                                if new.age_colors != config.age_colors { show_age_colors = new.age_colors; }
+                                if new.grid != config.grid { grid = new.grid; }
+                                if new.slashes != config.slashes { show_slashes = new.slashes; }
+                                if new.snippets != config.snippets { show_snippets = new.snippets; }
+                                if new.extensions != config.extensions { show_extensions = new.extensions; }
+                                if new.tilde != config.tilde { show_tilde = new.tilde; }
+                                if new.tree != config.tree { tree.enabled = new.tree; }
+                                if new.tree_depth != config.tree_depth { tree.depth = new.tree_depth; }
+                                if new.hidden != config.hidden { file_watcher.set_hidden(new.hidden); }
+                                if new.ignore != config.ignore && !show_ignored { file_watcher.set_ignore(new.ignore.clone()); }
+                                if new.dotfiles != config.dotfiles { file_watcher.set_dotfiles(new.dotfiles); }
+                                if new.group_order != config.group_order { file_watcher.set_groups(new.group_order); }
+                                if new.sort != config.sort { file_watcher.set_sort(new.sort); }
+                                if new.mouse != config.mouse {
+                                    mousemask(if new.mouse { ALL_MOUSE_EVENTS } else { 0 }, std::ptr::null_mut());
+                                }
+                                // The theme is set up again whatever changed, so that it can be tuned live
+                                if colors {
+                                    init_colors(&new.colors);
+                                }
+                                ls_colors = if colors && new.ls_colors { LsColors::from_env(LS_COLORS_FIRST_PAIR) } else { None };
+                                config = new;
+                                status = Some(("reloaded the config".to_string(), Instant::now()));
+                            }
+                            Err(err) => status = Some((err, Instant::now())),
+                        }
+                    }
+                    if c == '~' {
+                        show_tilde = !show_tilde;
+                    }
+                    if c == 'X' {
+                        show_extensions = !show_extensions;
+                    }
+                    if c == 'P' {
+                        show_snippets = !show_snippets;
+                    }
+                    if c == 'U' {
+                        show_owner = !show_owner;
+                    }
+                    if c == 'S' {
+                        show_dir_sizes = !show_dir_sizes;
+                    }
+                    if c == '\\' {
+                        show_slashes = !show_slashes;
+                    }
+                    if c == 'v' {
+                        grid = !grid;
+                    }
+                    if c == 'a' {
+                        show_age_colors = !show_age_colors;
+                    }
+                    if c == 'b' {
+                        exact_sizes = !exact_sizes;
+                    }
+                    if c == 'Z' {
+                        size_bars = !size_bars;
+                    }
+                    if c == ']' || c == '[' {
+                        let dirs: bool = c == ']';
+                        match next_of_type(&filez, selected, dirs, config.wrap_cursor) {
+                            Some(i) => selected = i as i32,
+                            None => status = Some((format!("no {} below", if dirs { "folder" } else { "file" }), Instant::now())),
+                        }
+                    }
+                    if c == 'g' {
+                        let initial: String = format!("{}{}", path.to_str().unwrap().trim_end_matches(is_separator), MAIN_SEPARATOR);
+                        if let Some(text) = prompt_path(&win, "go to: ", &initial, &path) {
+                            let target: PathBuf = resolve_dots(&path.join(text.trim()));
+                            if target.is_dir() {
+                                match navigate(&file_watcher, &selected_hist, root.as_deref(), target) {
+                                    Ok(nview) => {
+                                        selected = nview.selected;
+                                        scroll = nview.scroll;
+                                        break;
+                                    }
+                                    Err(locked) => status = Some((locked, Instant::now())),
+                                }
+                            }
+                            else if !text.trim().is_empty() {
+                                status = Some((format!("{} is not a directory", text.trim()), Instant::now()));
+                            }
+                        }
+                    }
+                    if c == 'G' {
+                        match git::repo_root(&path) {
+                            Some(repo) => {
+                                match navigate(&file_watcher, &selected_hist, root.as_deref(), repo) {
+                                    Ok(nview) => {
+                                        selected = nview.selected;
+                                        scroll = nview.scroll;
+                                        break;
+                                    }
+                                    Err(locked) => status = Some((locked, Instant::now())),
+                                }
+                            }
+                            None => status = Some(("not inside a git repository".to_string(), Instant::now())),
+                        }
+                    }
+                    if c == 'E' {
+                        tree.enabled = !tree.enabled;
+                    }
+                    if c == '+' || c == '-' {
+                        if let Some(f) = filez.get(selected as usize) {
+                            let depth: usize = depths.get(selected as usize).copied().unwrap_or(0);
+                            if c == '+' && f.is_dir() {
+                                tree.enabled = true;
+                                tree.expand(f.path());
+                            }
+                            else if c == '-' && f.is_dir() && tree.enabled && tree.is_expanded(f.path(), depth) {
+                                tree.collapse(f.path());
+                            }
+                            // Collapsing anything else collapses the directory it is in
+                            else if c == '-' && depth > 0 {
+                                let parent: PathBuf = parent_of(Path::new(f.path()));
+                                tree.collapse(parent.to_str().unwrap());
+                                if let Some(i) = filez.iter().position(|f| Path::new(f.path()) == parent) {
+                                    selected = i as i32;
+                                }
+                            }
+                        }
+                    }
+                    if c == 'I' {
+                        show_icons = !show_icons;
+                    }
+                    if c == 'R' {
+                        file_watcher.set_reverse(!file_watcher.reverse());
+                    }
+                    if c == 'i' {
+                        show_info = !show_info;
+                    }
+                    if c == 'n' && search.is_none() && allow_changes(&config, &mut status) {
+                        if let Some(name) = prompt(&win, "new (end with / for a folder): ", "") {
+                            let target: PathBuf = path.join(name.trim_end_matches(is_separator));
+                            let created = if name.trim_end_matches(is_separator).is_empty() {
+                                Ok(())
+                            }
+                            else if name.ends_with(is_separator) {
+                                fs::create_dir_all(&target)
+                            }
+                            else {
+                                fs::OpenOptions::new().write(true).create_new(true).open(&target).map(|_| ())
+                            };
+                            if let Err(err) = created {
+                                status = Some((format!("could not create {}: {}", name, err), Instant::now()));
+                            }
+                        }
+                    }
+                    if c == 'r' && allow_changes(&config, &mut status) {
+                        // Marked entries are renamed all at once after a pattern, the selected entry otherwise
+                        let entries: Vec<PathBuf> = filez.iter().filter(|f| marked.contains(f.path())).map(|f| PathBuf::from(f.path())).collect();
+                        if !entries.is_empty() {
+                            if let Some(pattern) = prompt(&win, "rename to ({n}, {name}, {ext}): ", "{name}{ext}").filter(|pattern| !pattern.is_empty()) {
+                                match ops::batch_names(&entries, &pattern) {
+                                    Ok(targets) => match rename_marked(&win, &entries, &targets) {
+                                        Some(errors) if !errors.is_empty() => {
+                                            let (name, err) = &errors[0];
+                                            status = Some((format!("could not rename {} entries, {}: {}", errors.len(), name, err), Instant::now()));
+                                        }
+                                        Some(_) => marked.clear(),
+                                        None => {}
+                                    },
+                                    Err(err) => status = Some((format!("nothing renamed, {}", err), Instant::now())),
+                                }
+                            }
+                        }
+                        else if let Some(f) = filez.get(selected as usize) {
+                            if let Some(name) = prompt(&win, "rename to: ", f.file_name()) {
+                                let target: PathBuf = parent_of(Path::new(f.path())).join(&name);
+                                if name.is_empty() || name == f.file_name() {
+                                    // Nothing to do
+                                }
+                                else if target.exists() && !ops::is_case_change(Path::new(f.path()), &target) {
+                                    status = Some((format!("{} already exists", name), Instant::now()));
+                                }
+                                else if let Err(err) = ops::rename(Path::new(f.path()), &target) {
+                                    status = Some((format!("could not rename {}: {}", f.file_name(), err), Instant::now()));
+                                }
+                            }
+                        }
+                    }
+                    if c == 'd' && allow_changes(&config, &mut status) {
+                        // Marked entries are deleted all at once, the selected entry otherwise
+                        let targets: Vec<&FileStat> = filez.iter().filter(|f| marked.contains(f.path())).collect();
+                        if !targets.is_empty() {
+                            if let Some(job) = delete_marked(&win, &targets, size_format, &mut dir_sizes) {
+                                jobs.push(job);
+                                marked.clear();
+                            }
+                        }
+                        else if let Some(f) = filez.get(selected as usize) {
+                            jobs.extend(delete_entry(&win, f));
+                        }
+                    }
+                    if c == 'l' && allow_changes(&config, &mut status) {
+                        if let Some(f) = filez.get(selected as usize) {
+                            let initial: String = format!("{} link", f.file_name());
+                            if let Some(text) = prompt_path(&win, &format!("link to {} at: ", f.file_name()), &initial, &path).filter(|text| !text.trim().is_empty()) {
+                                let link: PathBuf = resolve_dots(&path.join(text.trim()));
+                                if fs::symlink_metadata(&link).is_ok() {
+                                    status = Some((format!("{} already exists", link.display()), Instant::now()));
+                                }
+                                else {
+                                    match ops::symlink(Path::new(f.path()), &link) {
+                                        Ok(()) => pending_select = Some(link),
+                                        // Windows only lets administrators and Developer Mode create links
+                                        Err(err) if cfg!(windows) && err.raw_os_error() == Some(1314) => {
+                                            status = Some(("could not create the link: it needs Developer Mode or administrator rights".to_string(), Instant::now()));
+                                        }
+                                        Err(err) => status = Some((format!("could not create the link: {}", err), Instant::now())),
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    if c == 'D' && allow_changes(&config, &mut status) {
+                        if let Some(f) = filez.get(selected as usize) {
+                            match ops::duplicate(Path::new(f.path())) {
+                                Ok(copy) => pending_select = Some(copy),
+                                Err(err) => status = Some((format!("could not duplicate {}: {}", f.file_name(), err), Instant::now())),
+                            }
+                        }
+                    }
+                    if c == 'z' && allow_changes(&config, &mut status) {
+                        if let Some(f) = filez.get(selected as usize) {
+                            let initial: String = format!("{}.tar.gz", f.file_name());
+                            if let Some(name) = prompt(&win, "add to archive (.zip, .tar, .tar.gz): ", &initial).filter(|name| !name.is_empty()) {
+                                match archive::Format::detect(&name) {
+                                    Some(format) => {
+                                        let target: PathBuf = path.join(&name);
+                                        let entry: PathBuf = PathBuf::from(f.path());
+                                        pending_select = Some(target.clone());
+                                        jobs.push(Job::spawn(format!("archiving {}", f.file_name()), 0, move |progress| {
+                                            // Counting walks the whole tree, the spinner shows until it is done
+                                            progress.set_total(archive::count_entries(&entry));
+                                            match archive::add(&target, &entry, format, progress) {
+                                                Ok(()) => format!("added {} to {}", entry.display(), name),
+                                                Err(err) => format!("could not archive {}: {}", entry.display(), err),
+                                            }
+                                        }));
+                                    }
+                                    None => status = Some((format!("{} is not a .zip, .tar or .tar.gz archive", name), Instant::now())),
+                                }
+                            }
+                        }
+                    }
+                    #[cfg(unix)]
+                    if c == 'M' && allow_changes(&config, &mut status) {
+                        use std::os::unix::fs::PermissionsExt;
+                        if let Some((f, meta)) = filez.get(selected as usize).and_then(|f| Some((f, fs::metadata(f.path()).ok()?))) {
+                            let mode: u32 = meta.permissions().mode();
+                            if let Some(bits) = prompt::edit_mode(&win, &format!("permissions of {}", f.file_name()), mode) {
+                                // The special bits (setuid, sticky...) are kept as they were
+                                if let Err(err) = fs::set_permissions(f.path(), fs::Permissions::from_mode((mode & !0o777) | bits)) {
+                                    status = Some((format!("could not change the permissions of {}: {}", f.file_name(), err), Instant::now()));
+                                }
+                            }
+                        }
+                    }
+                    if c == 'c' || c == 'C' || c == 'y' {
+                        if let Some(f) = filez.get(selected as usize) {
+                            let text: String = match (c, &launch_dir) {
+                                ('C', _) => clipboard::shell_escape(f.path()),
+                                ('y', Some(base)) => clipboard::relative_to(Path::new(f.path()), base),
+                                _ => f.path().to_string(),
+                            };
+                            status = Some((match clipboard::copy(&text) {
+                                Ok(()) => format!("copied {}", text),
+                                Err(err) => format!("could not copy: {}", err),
+                            }, Instant::now()));
+                        }
+                    }
+                    if c == 'w' || c == 'W' {
+                        // What is copied is what is shown, filtered, sorted and expanded like the listing
+                        let details: Option<listing::Details> = (c == 'W').then_some((time_column, size_format));
+                        let text: String = listing::as_text(&filez, &depths, details);
+                        status = Some((match clipboard::copy(&text) {
+                            Ok(()) => format!("copied the {} entries of the listing", filez.len()),
+                            Err(err) => format!("could not copy: {}", err),
+                        }, Instant::now()));
+                    }
+                    if c == 'L' {
+                        if let Some(unlocked) = root.take() {
+                            status = Some((format!("unlocked {}", unlocked.display()), Instant::now()));
+                        }
+                        else {
+                            // The selected directory becomes the root, or the current one when a file is selected
+                            let target: PathBuf = filez.get(selected as usize)
+                                .filter(|f| f.is_dir())
+                                .map(|f| PathBuf::from(f.path()))
+                                .unwrap_or_else(|| path.clone());
+                            status = Some((format!("locked to {}, press L to unlock", target.display()), Instant::now()));
+                            root = Some(target.clone());
+                            if target != path {
+                                match navigate(&file_watcher, &selected_hist, root.as_deref(), target) {
+                                    Ok(nview) => {
+                                        selected = nview.selected;
+                                        scroll = nview.scroll;
+                                        break;
+                                    }
+                                    Err(locked) => status = Some((locked, Instant::now())),
+                                }
+                            }
+                        }
+                    }
+                    if c == '\x08' {
+                        match navigate(&file_watcher, &selected_hist, root.as_deref(), up_from(&file_watcher.path())) {
+                            Ok(nview) => {
+                                selected = nview.selected;
+                                scroll = nview.scroll;
+                                // The listing changed, the remaining inputs are left for the next frame
+                                break;
+                            }
+                            Err(locked) => status = Some((locked, Instant::now())),
+                        }
+                    }
+                    if c == '\x0a' {
+                        let f: FileStat = match filez.get(selected as usize) {
+                            Some(f) => f.clone(),
+                            None => continue,
+                        };
+                        if let Some(Err(err)) = f.is_dir().then(|| fs::read_dir(f.path())) {
+                            status = Some((format!("cannot open {}: {}", f.file_name(), err), Instant::now()));
+                        }
+                        else if f.is_dir() && !config.enter_empty && is_empty_dir(f.path()) {
+                            status = Some((format!("{} is empty", f.file_name()), Instant::now()));
+                        }
+                        else if f.is_dir() {
+                            match navigate(&file_watcher, &selected_hist, root.as_deref(), PathBuf::from(f.path())) {
+                                Ok(nview) => {
+                                    selected = nview.selected;
+                                    scroll = nview.scroll;
+                                    break;
+                                }
+                                Err(locked) => status = Some((locked, Instant::now())),
+                            }
+                        }
+                        else if let Some(format) = archive::Format::of_file(Path::new(f.path())).filter(|_| cfg!(feature = "archive") && !config.read_only) {
+                            let source: PathBuf = PathBuf::from(f.path());
+                            let dest: PathBuf = archive::extract_dir(&source, format);
+                            let dest_name: String = dest.file_name().unwrap_or_default().to_string_lossy().to_string();
+                            if confirm(&win, &format!("extract {} into {}/?", f.file_name(), dest_name)) {
+                                pending_select = Some(dest.clone());
+                                // The entries are checked by the job, reading a large archive takes a while
+                                jobs.push(Job::spawn(format!("extracting {}", f.file_name()), 0, move |progress| {
+                                    match archive::extract(&source, &dest, format, progress) {
+                                        Ok(()) => format!("extracted {} into {}", source.display(), dest.display()),
+                                        Err(err) => format!("could not extract {}: {}", source.display(), err),
+                                    }
+                                }));
+                            }
+                        }
+                        else if config.enter_previews {
+                            show_preview = !show_preview;
+                        }
+                        else if !open_anyway(&win, &config, &f) {
+                            // Left closed
+                        }
+                        else if let Err(err) = open::open(&win, config.opener_for(f.file_name()), f.path()) {
+                            status = Some((format!("could not open {}: {}", f.file_name(), err), Instant::now()));
+                        }
+                    }
+                    if c == 'p' {
+                        // The selected folder, or the current one when a file is selected
+                        let dir: PathBuf = filez.get(selected as usize).filter(|f| f.is_dir()).map(|f| PathBuf::from(f.path())).unwrap_or_else(|| path.clone());
+                        status = Some((match open::new_instance(&dir) {
+                            Ok(()) => format!("opened {} in another filez", dir.display()),
+                            Err(err) => format!("could not start another filez: {}", err),
+                        }, Instant::now()));
+                    }
+                    if c == 'O' {
+                        if let Some(f) = filez.get(selected as usize) {
+                            if let Err(err) = open::reveal(Path::new(f.path())) {
+                                status = Some((format!("could not show {} in the file manager: {}", f.file_name(), err), Instant::now()));
+                            }
+                        }
+                    }
+                    if c == 'o' {
+                        if let Some(f) = filez.get(selected as usize).filter(|f| !f.is_dir() && open_anyway(&win, &config, f)) {
+                            if let Err(err) = open::open(&win, config.opener_for(f.file_name()), f.path()) {
+                                status = Some((format!("could not open {}: {}", f.file_name(), err), Instant::now()));
+                            }
+                        }
+                    }
+                }
+                Input::KeyMouse => {
+                    if let Ok(evt) = getmouse() {
+                        /*
+                        Left:
+                            4    : click
+                            2    : press
+                            1    : release
+                        Right:
+                            4096 : click
+                            2048 : press
+                            1024 : release
+                        Middle:
+                            128  : click
+                            64   : press
+                            32   : release
+                        Scroll:
+                            65536 : up
+                            2097152 : down
+                        */
+                        let scroll_step: i32 = if grid { rows } else { 1 };
+                        if evt.bstate & 65536 != 0 { scroll -= scroll_step; }
+                        if evt.bstate & 2097152 != 0 { scroll += scroll_step; }
+                        if evt.bstate & BUTTON1_CLICKED != 0 && evt.y == 0 {
+                            if let Some(ancestor) = breadcrumb_at(&file_watcher.path(), evt.x + header_offset as i32) {
+                                match navigate(&file_watcher, &selected_hist, root.as_deref(), ancestor) {
+                                    Ok(nview) => {
+                                        selected = nview.selected;
+                                        scroll = nview.scroll;
+                                        break;
+                                    }
+                                    Err(locked) => status = Some((locked, Instant::now())),
+                                }
+                            }
+                        }
+                        // Clicking the target of a link goes there, the target starts after the ` -> `
+                        let column: i32 = evt.x / cell_width;
+                        let clicked: Option<&FileStat> = filez.get((column * rows + evt.y - 1 + scroll).max(0) as usize).filter(|_| evt.y > 0 && evt.y <= rows && column < columns);
+                        let link: Option<PathBuf> = clicked.and_then(|f| {
+                            let target: &Path = f.link().filter(|_| config.link_targets)?;
+                            let start: i32 = link_x + 4;
+                            (evt.x >= start && evt.x < start + target.to_string_lossy().chars().count() as i32)
+                                .then(|| parent_of(Path::new(f.path())).join(target))
+                        });
+                        if let Some(target) = link.filter(|_| evt.bstate & BUTTON1_CLICKED != 0) {
+                            if !target.exists() {
+                                status = Some((format!("{} does not exist", target.display()), Instant::now()));
+                            }
+                            else {
+                                let target: PathBuf = normalize_path(target);
+                                // Files get selected in their directory
+                                let dir: PathBuf = if target.is_dir() { target.clone() } else { parent_of(&target) };
+                                match navigate(&file_watcher, &selected_hist, root.as_deref(), dir) {
+                                    Ok(nview) => {
+                                        if !target.is_dir() { pending_select = Some(target); }
+                                        selected = nview.selected;
+                                        scroll = nview.scroll;
+                                        break;
+                                    }
+                                    Err(locked) => status = Some((locked, Instant::now())),
+                                }
+                            }
+                        }
+                        // A middle click enters a directory, or opens a file with the default app without leaving the listing
+                        if let Some(f) = clicked.filter(|_| evt.bstate & BUTTON2_CLICKED != 0) {
+                            if f.is_dir() {
+                                match fs::read_dir(f.path()) {
+                                    Ok(_) => {
+                                        match navigate(&file_watcher, &selected_hist, root.as_deref(), PathBuf::from(f.path())) {
+                                            Ok(nview) => {
+                                                selected = nview.selected;
+                                                scroll = nview.scroll;
+                                                break;
+                                            }
+                                            Err(locked) => status = Some((locked, Instant::now())),
+                                        }
+                                    }
+                                    Err(err) => status = Some((format!("cannot open {}: {}", f.file_name(), err), Instant::now())),
+                                }
+                            }
+                            else {
+                                status = Some((match open::open_background(&config.opener, f.path()) {
+                                    Ok(()) => format!("opened {} in the background", f.file_name()),
+                                    Err(err) => format!("could not open {}: {}", f.file_name(), err),
+                                }, Instant::now()));
+                            }
+                        }
+                    }
+                },
+                _ => {}
+            }
+        }
+        if movement != 0 {
+            selected = move_selection(selected, movement, filez.len(), config.wrap_cursor);
+        }
+        tracked_selection = filez.get(selected as usize).map(|f| f.path().to_string());
+
+    }
+
+    // Lets a shell wrapper `cd` into the last browsed directory
+    let cd_written: io::Result<()> = match (cd_on_quit, &config.cd_file) {
+        (true, Some(cd_file)) => fs::write(cd_file, file_watcher.path().to_string_lossy().as_bytes()),
+        _ => Ok(()),
+    };
+
+    endwin();
+
+    if let Err(err) = cd_written {
+        eprintln!("filez: could not write the cd file: {}", err);
+        process::exit(1);
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rows_are_capped_by_the_terminal() {
+        assert_eq!(body_rows(24, 0), 22);
+        assert_eq!(body_rows(24, 10), 10);
+        assert_eq!(body_rows(24, 100), 22);
+        // Resizing smaller than the limit shrinks the listing with the terminal
+        assert_eq!(body_rows(8, 10), 6);
+        assert_eq!(body_rows(1, 10), 0);
+    }
+
+    fn home_of(user: &str) -> Option<PathBuf> {
+        match user {
+            "" => Some(PathBuf::from("/home/me")),
+            "bob" => Some(PathBuf::from("/home/bob")),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn tildes_expand_to_home_directories() {
+        assert_eq!(expand_tilde_with("~", home_of), Path::new("/home/me"));
+        assert_eq!(expand_tilde_with("~/Downloads", home_of), Path::new("/home/me/Downloads"));
+        assert_eq!(expand_tilde_with("~bob", home_of), Path::new("/home/bob"));
+        assert_eq!(expand_tilde_with("~bob/src/x", home_of), Path::new("/home/bob/src/x"));
+    }
+
+    #[test]
+    fn other_paths_are_kept() {
+        assert_eq!(expand_tilde_with("~nobody/x", home_of), Path::new("~nobody/x"));
+        assert_eq!(expand_tilde_with("/tmp/~x", home_of), Path::new("/tmp/~x"));
+        assert_eq!(expand_tilde_with("a~b", home_of), Path::new("a~b"));
+        assert_eq!(expand_tilde_with("~", |_| None), Path::new("~"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn users_are_looked_up() {
+        assert!(expand_tilde("~root").is_absolute());
+        assert_eq!(expand_tilde("~no-such-user-here/x"), Path::new("~no-such-user-here/x"));
+    }
+
+    #[test]
+    fn home_is_shown_as_tilde() {
+        let home: &Path = Path::new("/home/me");
+        assert_eq!(tildify(Path::new("/home/me"), home), "~");
+        assert_eq!(tildify(Path::new("/home/me/src"), home), format!("~{}src", MAIN_SEPARATOR));
+        assert_eq!(tildify(Path::new("/home/meh"), home), "/home/meh");
+        assert_eq!(tildify(Path::new("/etc"), home), "/etc");
+    }
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir: PathBuf = std::env::temp_dir().join(format!("filez-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("sub").join("inner.txt"), "").unwrap();
+        fs::write(dir.join("outer.txt"), "").unwrap();
+        dir
+    }
+
+    fn names(file_watcher: &FileWatcher) -> Vec<String> {
+        file_watcher.filez().iter().map(|f| f.file_name().to_string()).collect()
+    }
+
+    #[test]
+    fn rows_of_expanded_directories_are_selected_in_place() {
+        let dir: PathBuf = scratch_dir("tree");
+        let file_watcher: FileWatcher = FileWatcher::new(dir.clone(), SortMode::Natural);
+        let mut entries: Vec<FileStat> = fs::read_dir(&dir).unwrap().flatten().map(FileStat::from).collect();
+        file_watcher.sort_listing(&mut entries);
+        assert!(file_watcher.publish(&dir, file_watcher.generation(), entries, 2));
+        let mut tree: Tree = Tree::new(true, 0);
+        tree.expand(dir.join("sub").to_str().unwrap());
+        let (rows, depths) = shown_entries(&file_watcher, &mut tree);
+        // Enter on the second row opens the file inside of `sub`, not the second entry of the directory
+        assert_eq!(rows[1].path(), dir.join("sub").join("inner.txt").to_str().unwrap());
+        assert_eq!(depths[..3], [0, 1, 0]);
+        assert_eq!(rows[2].file_name(), "outer.txt");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn permission_changes_are_seen_by_rescans() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir: PathBuf = scratch_dir("chmod");
+        let before: FileStat = dir.join("sub").into();
+        fs::set_permissions(dir.join("sub"), fs::Permissions::from_mode(0o700)).unwrap();
+        let after: FileStat = dir.join("sub").into();
+        assert_eq!(before.modified(), after.modified());
+        assert!(before.changed().is_some());
+        assert_ne!(before.changed(), after.changed());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn navigating_before_the_first_scan_waits_for_the_target() {
+        let dir: PathBuf = scratch_dir("navigate");
+        let file_watcher: FileWatcher = FileWatcher::new(dir.clone(), SortMode::Natural);
+        assert_eq!(file_watcher.path2(), None);
+        let scanner: FileWatcher = file_watcher.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            spawn_watcher(scanner, 100, Duration::from_millis(100));
+        });
+        let view: View = navigate(&file_watcher, &HashMap::new(), None, dir.join("sub")).unwrap();
+        assert_eq!(file_watcher.path2(), Some(dir.join("sub")));
+        assert_eq!(names(&file_watcher), ["inner.txt"]);
+        assert_eq!(view.selected, 0);
+        // Going back up selects the directory that was left
+        let view: View = navigate(&file_watcher, &HashMap::new(), None, dir.clone()).unwrap();
+        assert_eq!(file_watcher.path2(), Some(dir.clone()));
+        assert_eq!(names(&file_watcher)[view.selected as usize], "sub");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn navigating_gives_up_on_a_listing_that_does_not_come() {
+        let dir: PathBuf = scratch_dir("navigate-timeout");
+        let file_watcher: FileWatcher = FileWatcher::new(dir.clone(), SortMode::Natural);
+        let entries: Vec<FileStat> = fs::read_dir(&dir).unwrap().flatten().map(FileStat::from).collect();
+        assert!(file_watcher.publish(&dir, file_watcher.generation(), entries, 2));
+        let since: Instant = Instant::now();
+        navigate(&file_watcher, &HashMap::new(), None, dir.join("sub")).unwrap();
+        assert!(since.elapsed() >= NAVIGATE_TIMEOUT);
+        // The listing of the previous directory isn't shown as the one of the target
+        assert_eq!(file_watcher.path2(), Some(dir.clone()));
+        assert!(file_watcher.filez().is_empty());
+        assert!(!file_watcher.publish(&dir, 1, vec![], 0));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn navigating_out_of_the_locked_folder_is_refused() {
+        let dir: PathBuf = scratch_dir("navigate-locked");
+        let file_watcher: FileWatcher = FileWatcher::new(dir.join("sub"), SortMode::Natural);
+        let locked: Result<View, String> = navigate(&file_watcher, &HashMap::new(), Some(&dir.join("sub")), dir.clone());
+        assert_eq!(locked.err(), Some(format!("locked to {}, press L to unlock", dir.join("sub").display())));
+        assert_eq!(file_watcher.path(), dir.join("sub"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
 }
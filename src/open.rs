@@ -0,0 +1,161 @@
+use crosscurses::*;
+use std::{
+    env,
+    io::{self, BufRead},
+    path::Path,
+    process::{Command, Stdio},
+    thread,
+};
+
+use crate::config::Opener;
+
+/// Opens `path` with `opener`
+///
+/// Detached commands are left running on their own, the others take over the terminal until they exit
+pub fn open(win: &Window, opener: &Opener, path: &str) -> io::Result<()> {
+    let mut command: Command = build(opener, path)?;
+    if opener.detach {
+        detach(command)
+    }
+    else {
+        endwin();
+        let status = command.status();
+        win.refresh();
+        status.map(|_| ())
+    }
+}
+
+/// Opens `path` with `opener` in the background, even if it is a terminal app, which then gets no terminal
+pub fn open_background(opener: &Opener, path: &str) -> io::Result<()> {
+    let mut command: Command = build(opener, path)?;
+    command.stdin(Stdio::null()).stdout(Stdio::null()).stderr(Stdio::null());
+    detach(command)
+}
+
+/// Returns the editor of the user, `$VISUAL` or `$EDITOR`
+pub fn editor() -> Opener {
+    let command: String = ["VISUAL", "EDITOR"].iter()
+        .filter_map(|var| env::var(var).ok())
+        .find(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| if cfg!(windows) { "notepad" } else { "vi" }.to_string());
+    Opener { command, detach: false }
+}
+
+/// Compares two files with `$DIFFTOOL`, or `diff -u` which waits for Enter before going back to the listing
+pub fn diff(win: &Window, a: &str, b: &str) -> io::Result<()> {
+    let tool: Option<String> = env::var("DIFFTOOL").ok().filter(|tool| !tool.trim().is_empty());
+    let parts: Vec<String> = split(tool.as_deref().unwrap_or("diff -u"));
+    let mut command: Command = Command::new(&parts[0]);
+    command.args(&parts[1..]).arg(a).arg(b);
+    endwin();
+    // Differences make diff exit with an error code, which isn't a failure here
+    let status = command.status();
+    if tool.is_none() && status.is_ok() {
+        println!("\n[Enter to go back]");
+        let _ = io::stdin().lock().read_line(&mut String::new());
+    }
+    win.refresh();
+    status.map(|_| ())
+}
+
+/// Shows `path` in the file manager of the system, selected in its folder where the file manager allows it
+///
+/// Linux file managers don't agree on a way to select an entry, so the folder is just opened
+pub fn reveal(path: &Path) -> io::Result<()> {
+    let mut command: Command;
+    if cfg!(windows) {
+        command = Command::new("explorer");
+        // explorer wants the path glued to the flag
+        let mut arg = std::ffi::OsString::from("/select,");
+        arg.push(path);
+        command.arg(arg);
+    }
+    else if cfg!(target_os = "macos") {
+        command = Command::new("open");
+        command.arg("-R").arg(path);
+    }
+    else {
+        command = Command::new("xdg-open");
+        command.arg(path.parent().unwrap_or(path));
+    }
+    // Whatever the file manager prints would end up over the listing
+    command.stdin(Stdio::null()).stdout(Stdio::null()).stderr(Stdio::null());
+    detach(command)
+}
+
+/// Starts another filez in `dir`, in a new tmux pane when running inside tmux, in a new `$TERMINAL` window otherwise
+pub fn new_instance(dir: &Path) -> io::Result<()> {
+    let exe = env::current_exe()?;
+    let mut command: Command;
+    if env::var_os("TMUX").is_some() {
+        command = Command::new("tmux");
+        command.arg("split-window").arg("-h").arg("-c").arg(dir).arg(exe).arg(dir);
+    }
+    else if let Some(terminal) = env::var("TERMINAL").ok().filter(|terminal| !terminal.trim().is_empty()) {
+        let parts: Vec<String> = split(&terminal);
+        command = Command::new(&parts[0]);
+        command.args(&parts[1..]).arg("-e").arg(exe).arg(dir).current_dir(dir);
+    }
+    else if cfg!(windows) {
+        // `start` opens a console of its own, its first quoted argument is the title
+        command = Command::new("cmd");
+        command.arg("/c").arg("start").arg("").arg(exe).arg(dir);
+    }
+    else {
+        return Err(io::Error::other("no terminal to run it in, set $TERMINAL"));
+    }
+    // The new filez has a terminal of its own, this one is left to the current one
+    command.stdin(Stdio::null()).stdout(Stdio::null()).stderr(Stdio::null());
+    detach(command)
+}
+
+/// Starts a command that runs on its own
+fn detach(mut command: Command) -> io::Result<()> {
+    // In a process group of its own, Ctrl-C in the terminal and quitting filez leave it alone
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut command, 0);
+    let mut child = command.spawn()?;
+    // Reaps the process once it is done so it doesn't linger around
+    thread::spawn(move || child.wait());
+    Ok(())
+}
+
+/// Builds the command line of an opener for a given file
+fn build(opener: &Opener, path: &str) -> io::Result<Command> {
+    let mut parts: Vec<String> = split(&opener.command);
+    if parts.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "empty open command"));
+    }
+    if parts.iter().any(|part| part == "{}") {
+        for part in parts.iter_mut().filter(|part| *part == "{}") {
+            *part = path.to_string();
+        }
+    }
+    else {
+        parts.push(path.to_string());
+    }
+    let mut command: Command = Command::new(&parts[0]);
+    command.args(&parts[1..]);
+    Ok(command)
+}
+
+/// Splits a command line on whitespace, double quotes group words together
+fn split(line: &str) -> Vec<String> {
+    let mut parts: Vec<String> = vec![];
+    let mut current: Option<String> = None;
+    let mut quoted: bool = false;
+    for c in line.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                current.get_or_insert_with(String::new);
+            }
+            c if c.is_whitespace() && !quoted => {
+                if let Some(part) = current.take() { parts.push(part); }
+            }
+            c => current.get_or_insert_with(String::new).push(c),
+        }
+    }
+    if let Some(part) = current { parts.push(part); }
+    parts
+}
@@ -0,0 +1,240 @@
+use std::{
+    fs,
+    io,
+    path::{Path, PathBuf},
+};
+
+use crate::job::Progress;
+
+/// Copies `path` next to itself under a free name (`name copy.ext`, `name copy 2.ext`...),
+/// directories are copied along with their contents and symbolic links as links
+///
+/// Returns the path of the copy
+pub fn duplicate(path: &Path) -> io::Result<PathBuf> {
+    let target: PathBuf = copy_name(path);
+    let typ: fs::FileType = fs::symlink_metadata(path)?.file_type();
+    if typ.is_dir() {
+        copy_dir(path, &target)?;
+    }
+    else if typ.is_symlink() {
+        copy_link(path, &target)?;
+    }
+    else {
+        fs::copy(path, &target)?;
+    }
+    Ok(target)
+}
+
+/// Creates a symbolic link at `link` that points to `target`
+#[cfg(unix)]
+pub fn symlink(target: &Path, link: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+/// Creates a symbolic link at `link` that points to `target`, Windows tells links to directories apart
+#[cfg(windows)]
+pub fn symlink(target: &Path, link: &Path) -> io::Result<()> {
+    if target.is_dir() {
+        std::os::windows::fs::symlink_dir(target, link)
+    }
+    else {
+        std::os::windows::fs::symlink_file(target, link)
+    }
+}
+
+/// Renames `from` to `to`, which includes changing only the case of the name on case-insensitive file systems
+pub fn rename(from: &Path, to: &Path) -> io::Result<()> {
+    if !is_case_change(from, to) {
+        return fs::rename(from, to);
+    }
+    // Both names are the same file to the system, which may keep the old case, so the file goes through another name first
+    let name: String = from.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+    let temp: PathBuf = (1..).map(|n: u32| from.with_file_name(format!(".{}.rename{}", name, n)))
+        .find(|temp| fs::symlink_metadata(temp).is_err())
+        .unwrap();
+    fs::rename(from, &temp)?;
+    fs::rename(&temp, to).inspect_err(|_| { let _ = fs::rename(&temp, from); })
+}
+
+/// Returns whether renaming `from` to `to` only changes the case of its name, `to` being the same file as `from`
+pub fn is_case_change(from: &Path, to: &Path) -> bool {
+    let (Some(from_name), Some(to_name)) = (from.file_name(), to.file_name()) else {
+        return false;
+    };
+    let (from_name, to_name) = (from_name.to_string_lossy(), to_name.to_string_lossy());
+    from.parent() == to.parent() && from_name != to_name && from_name.to_lowercase() == to_name.to_lowercase() && same_file(from, to)
+}
+
+/// Returns whether both paths lead to the same file
+#[cfg(unix)]
+fn same_file(a: &Path, b: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (fs::symlink_metadata(a), fs::symlink_metadata(b)) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+        _ => false,
+    }
+}
+
+/// Returns whether both paths lead to the same file, file systems being case-insensitive there
+#[cfg(not(unix))]
+fn same_file(_a: &Path, b: &Path) -> bool {
+    fs::symlink_metadata(b).is_ok()
+}
+
+/// Deletes `path` and everything under it, each entry deleted is counted in `progress`,
+/// symbolic links are deleted rather than followed
+///
+/// Entries that can't be deleted are skipped and returned, the deletion stops early once cancelled
+pub fn remove_all(path: &Path, progress: &Progress) -> Vec<(PathBuf, io::Error)> {
+    let mut errors: Vec<(PathBuf, io::Error)> = vec![];
+    remove_tree(path, progress, &mut errors);
+    errors
+}
+
+fn remove_tree(path: &Path, progress: &Progress, errors: &mut Vec<(PathBuf, io::Error)>) {
+    if progress.is_cancelled() { return }
+    let typ: fs::FileType = match fs::symlink_metadata(path) {
+        Ok(meta) => meta.file_type(),
+        Err(err) => return errors.push((path.to_path_buf(), err)),
+    };
+    let removed: io::Result<()> = if typ.is_dir() {
+        let failed: usize = errors.len();
+        match fs::read_dir(path) {
+            Ok(entries) => for entry in entries {
+                match entry {
+                    Ok(entry) => remove_tree(&entry.path(), progress, errors),
+                    Err(err) => errors.push((path.to_path_buf(), err)),
+                }
+            },
+            Err(err) => return errors.push((path.to_path_buf(), err)),
+        }
+        // A directory that still has entries left can't go, which was already reported
+        if progress.is_cancelled() || errors.len() > failed { return }
+        fs::remove_dir(path)
+    }
+    else {
+        // Links to directories are directories of their own on Windows
+        fs::remove_file(path).or_else(|err| if cfg!(windows) && typ.is_symlink() { fs::remove_dir(path) } else { Err(err) })
+    };
+    match removed {
+        Ok(()) => progress.step(),
+        Err(err) => errors.push((path.to_path_buf(), err)),
+    }
+}
+
+/// Names each of `paths` after `pattern`, where `{n}` is the position of the entry starting from 1,
+/// `{name}` its name without the extension and `{ext}` its extension with the dot (directories have none)
+///
+/// Fails if two entries would get the same name or a name that is already taken
+pub fn batch_names(paths: &[PathBuf], pattern: &str) -> Result<Vec<PathBuf>, String> {
+    let mut targets: Vec<PathBuf> = vec![];
+    for (i, path) in paths.iter().enumerate() {
+        let name: String = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+        let (stem, ext): (&str, &str) = match name.rfind('.') {
+            Some(i) if i > 0 && !path.is_dir() => name.split_at(i),
+            _ => (name.as_str(), ""),
+        };
+        let new_name: String = pattern.replace("{n}", &(i+1).to_string()).replace("{name}", stem).replace("{ext}", ext);
+        if new_name.is_empty() || new_name.contains('/') || (cfg!(windows) && new_name.contains('\\')) {
+            return Err(format!("'{}' is not a valid name", new_name));
+        }
+        let target: PathBuf = path.with_file_name(&new_name);
+        if targets.contains(&target) {
+            return Err(format!("more than one entry would be named {}", new_name));
+        }
+        if target != *path && fs::symlink_metadata(&target).is_ok() && !is_case_change(path, &target) {
+            return Err(format!("{} already exists", new_name));
+        }
+        targets.push(target);
+    }
+    Ok(targets)
+}
+
+/// Finds the first name of the `name copy N.ext` form that isn't taken yet
+fn copy_name(path: &Path) -> PathBuf {
+    let name: String = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+    // Only files have an extension, `.name` files don't
+    let (stem, ext): (&str, &str) = match name.rfind('.') {
+        Some(i) if i > 0 && !path.is_dir() => name.split_at(i),
+        _ => (name.as_str(), ""),
+    };
+    // Duplicating a copy gives the next copy rather than a copy of a copy
+    let stem: &str = match stem.rsplit_once(" copy") {
+        Some((base, n)) if n.is_empty() || n.strip_prefix(' ').is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit())) => base,
+        _ => stem,
+    };
+    (1..).map(|n: u32| {
+            let copy: String = if n == 1 { format!("{} copy{}", stem, ext) } else { format!("{} copy {}{}", stem, n, ext) };
+            path.with_file_name(copy)
+        })
+        .find(|target| fs::symlink_metadata(target).is_err())
+        .unwrap()
+}
+
+/// Copies the directory `from` to `to` recursively, symbolic links are copied as links
+fn copy_dir(from: &Path, to: &Path) -> io::Result<()> {
+    fs::create_dir(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target: PathBuf = to.join(entry.file_name());
+        let typ = entry.file_type()?;
+        if typ.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        }
+        else if typ.is_symlink() {
+            copy_link(&entry.path(), &target)?;
+        }
+        else {
+            fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+/// Creates a link at `to` that points where the symbolic link `from` does
+fn copy_link(from: &Path, to: &Path) -> io::Result<()> {
+    let target: PathBuf = fs::read_link(from)?;
+    // The target can be relative to the link, what it is has to be asked through the link itself
+    #[cfg(windows)]
+    if fs::metadata(from).is_ok_and(|meta| meta.is_dir()) {
+        return std::os::windows::fs::symlink_dir(target, to);
+    }
+    #[cfg(windows)]
+    return std::os::windows::fs::symlink_file(target, to);
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(target, to)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir: PathBuf = std::env::temp_dir().join(format!("filez-ops-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("dir")).unwrap();
+        fs::write(dir.join("dir").join("file.txt"), "text").unwrap();
+        symlink(Path::new("dir"), &dir.join("link")).unwrap();
+        symlink(Path::new("file.txt"), &dir.join("dir").join("file link")).unwrap();
+        dir
+    }
+
+    #[test]
+    fn links_to_directories_are_duplicated_as_links() {
+        let dir: PathBuf = scratch_dir("link");
+        let copy: PathBuf = duplicate(&dir.join("link")).unwrap();
+        assert_eq!(copy, dir.join("link copy"));
+        assert!(fs::symlink_metadata(&copy).unwrap().file_type().is_symlink());
+        assert_eq!(fs::read_link(&copy).unwrap(), Path::new("dir"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn links_inside_directories_stay_links() {
+        let dir: PathBuf = scratch_dir("tree");
+        let copy: PathBuf = duplicate(&dir.join("dir")).unwrap();
+        assert_eq!(fs::read_to_string(copy.join("file.txt")).unwrap(), "text");
+        assert_eq!(fs::read_link(copy.join("file link")).unwrap(), Path::new("file.txt"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,84 @@
+use std::{
+    collections::HashMap,
+    fs::Metadata,
+};
+
+/// Resolves the owners of files to names, each id is only looked up once
+#[derive(Default)]
+pub struct Owners {
+    users: HashMap<u32, String>,
+    groups: HashMap<u32, String>,
+}
+
+impl Owners {
+
+    /// Returns the `user:group` owning a file, ids without a name are kept as numbers
+    #[cfg(unix)]
+    pub fn owner(&mut self, meta: &Metadata) -> Option<String> {
+        use std::os::unix::fs::MetadataExt;
+        let user: &String = self.users.entry(meta.uid()).or_insert_with(|| user_name(meta.uid()).unwrap_or_else(|| meta.uid().to_string()));
+        let user: String = user.clone();
+        let group: &String = self.groups.entry(meta.gid()).or_insert_with(|| group_name(meta.gid()).unwrap_or_else(|| meta.gid().to_string()));
+        Some(format!("{}:{}", user, group))
+    }
+
+    /// Owners aren't shown outside of Unix
+    #[cfg(not(unix))]
+    pub fn owner(&mut self, _meta: &Metadata) -> Option<String> {
+        None
+    }
+
+}
+
+/// Looks the name of a user up in the user database
+#[cfg(unix)]
+fn user_name(uid: u32) -> Option<String> {
+    lookup(|buf| {
+        let mut entry: libc::passwd = unsafe { std::mem::zeroed() };
+        let mut found: *mut libc::passwd = std::ptr::null_mut();
+        let err = unsafe { libc::getpwuid_r(uid, &mut entry, buf.as_mut_ptr(), buf.len(), &mut found) };
+        (err, (!found.is_null()).then_some(entry.pw_name))
+    })
+}
+
+/// Looks the name of a group up in the group database
+#[cfg(unix)]
+fn group_name(gid: u32) -> Option<String> {
+    lookup(|buf| {
+        let mut entry: libc::group = unsafe { std::mem::zeroed() };
+        let mut found: *mut libc::group = std::ptr::null_mut();
+        let err = unsafe { libc::getgrgid_r(gid, &mut entry, buf.as_mut_ptr(), buf.len(), &mut found) };
+        (err, (!found.is_null()).then_some(entry.gr_name))
+    })
+}
+
+/// Looks the home directory of a user up by name in the user database
+#[cfg(unix)]
+pub fn user_home(name: &str) -> Option<String> {
+    let name: std::ffi::CString = std::ffi::CString::new(name).ok()?;
+    lookup(|buf| {
+        let mut entry: libc::passwd = unsafe { std::mem::zeroed() };
+        let mut found: *mut libc::passwd = std::ptr::null_mut();
+        let err = unsafe { libc::getpwnam_r(name.as_ptr(), &mut entry, buf.as_mut_ptr(), buf.len(), &mut found) };
+        (err, (!found.is_null()).then_some(entry.pw_dir))
+    })
+}
+
+/// Other users aren't looked up outside of Unix
+#[cfg(not(unix))]
+pub fn user_home(_name: &str) -> Option<String> {
+    None
+}
+
+/// Runs a `get*_r` lookup, growing its buffer until the entry fits
+#[cfg(unix)]
+fn lookup(mut get: impl FnMut(&mut Vec<libc::c_char>) -> (libc::c_int, Option<*mut libc::c_char>)) -> Option<String> {
+    let mut buf: Vec<libc::c_char> = vec![0; 1024];
+    loop {
+        match get(&mut buf) {
+            (libc::ERANGE, _) if buf.len() < 1 << 20 => buf.resize(buf.len() * 2, 0),
+            (0, Some(name)) => return Some(unsafe { std::ffi::CStr::from_ptr(name) }.to_string_lossy().to_string()),
+            _ => return None,
+        }
+    }
+}
@@ -0,0 +1,94 @@
+/// Returns whether `name` matches the glob `pattern` as a whole, `*` stands for any text and `?` for any character
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let (pattern, name): (Vec<char>, Vec<char>) = (pattern.chars().collect(), name.chars().collect());
+    let (mut p, mut n): (usize, usize) = (0, 0);
+    // Where the last `*` was and how much of the name it takes so far, to give it more when the rest doesn't match
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => { star = Some((p, n)); p += 1; }
+            Some(&c) if c == '?' || c == name[n] => { p += 1; n += 1; }
+            _ => match star {
+                Some((star_p, star_n)) => { star = Some((star_p, star_n + 1)); p = star_p + 1; n = star_n + 1; }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// What `*` matches the names of entries against, a regular expression with the `regex` feature, a glob otherwise
+pub struct NamePattern {
+    #[cfg(feature = "regex")]
+    regex: regex::Regex,
+    #[cfg(not(feature = "regex"))]
+    glob: String,
+}
+
+impl NamePattern {
+
+    /// Parses `pattern`, the error describes what is wrong with it
+    #[cfg(feature = "regex")]
+    pub fn new(pattern: &str) -> Result<Self, String> {
+        // The default size limit keeps patterns like `a{1000000}` from taking all the memory
+        regex::Regex::new(pattern)
+            .map(|regex| NamePattern { regex })
+            .map_err(|err| err.to_string().lines().last().unwrap_or("invalid").trim_start_matches("error: ").to_string())
+    }
+
+    /// Globs are always valid
+    #[cfg(not(feature = "regex"))]
+    pub fn new(pattern: &str) -> Result<Self, String> {
+        Ok(NamePattern { glob: pattern.to_string() })
+    }
+
+    /// Returns whether the pattern matches `name`, somewhere in it for a regular expression, as a whole for a glob
+    pub fn is_match(&self, name: &str) -> bool {
+        #[cfg(feature = "regex")]
+        return self.regex.is_match(name);
+        #[cfg(not(feature = "regex"))]
+        return glob_match(&self.glob, name);
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn globs_match_whole_names() {
+        assert!(glob_match("*.pyc", "cache.pyc"));
+        assert!(glob_match("node_modules", "node_modules"));
+        assert!(glob_match("f?le*", "file.txt"));
+        assert!(!glob_match("*.pyc", "cache.pyc.bak"));
+        assert!(!glob_match("target", "targets"));
+        assert!(glob_match("*a*b", "xxaxxb"));
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn regexes_match_anywhere_in_names() {
+        assert!(NamePattern::new(r"\.log$").unwrap().is_match("app.log"));
+        assert!(!NamePattern::new(r"\.log$").unwrap().is_match("app.log.gz"));
+        assert!(NamePattern::new("^f[0-9]{2}").unwrap().is_match("f01"));
+        assert!(NamePattern::new("(unclosed").is_err());
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn pathological_regexes_are_harmless() {
+        // Matched in linear time rather than by backtracking
+        assert!(!NamePattern::new("(a|aa)*c").unwrap().is_match(&"a".repeat(5000)));
+        // Huge counts are refused rather than overflowing the stack
+        assert!(NamePattern::new("a{1000000}").is_err());
+        assert!(NamePattern::new("(){100000}").map_or(true, |pattern| pattern.is_match("x")));
+    }
+
+    #[cfg(not(feature = "regex"))]
+    #[test]
+    fn globs_are_used_without_regexes() {
+        assert!(NamePattern::new("*.log").unwrap().is_match("app.log"));
+        assert!(!NamePattern::new("*.log").unwrap().is_match("app.log.gz"));
+    }
+}
@@ -0,0 +1,343 @@
+use crosscurses::*;
+use std::{
+    fs,
+    path::{is_separator, Path, PathBuf, MAIN_SEPARATOR},
+    sync::atomic::Ordering,
+};
+
+use crate::INTERRUPTED;
+use crate::jump::{fuzzy_score, DirIndex};
+use crate::sort::natural_cmp;
+
+/// How long a popup waits for a key before checking for an interrupt again, in milliseconds
+const POPUP_TIMEOUT_MS: i32 = 100;
+
+/// Asks for a line of text on the last line of the screen, starting with `initial`
+///
+/// Returns `None` if the user cancelled with Escape or `Ctrl-C`
+pub fn prompt(win: &Window, label: &str, initial: &str) -> Option<String> {
+    edit_line(win, label, initial, None)
+}
+
+/// Asks for a path like `prompt`, Tab completes directory names relative to `base`
+/// and cycles through the matches when pressed again (Shift-Tab goes back)
+pub fn prompt_path(win: &Window, label: &str, initial: &str, base: &Path) -> Option<String> {
+    edit_line(win, label, initial, Some(base))
+}
+
+/// Edits a line of text on the last line of the screen, paths are completed if `base` is given
+fn edit_line(win: &Window, label: &str, initial: &str, base: Option<&Path>) -> Option<String> {
+    let mut text: String = initial.to_string();
+    // The matches of the last completion and which one is shown, until something else is typed
+    let mut completions: Option<(Vec<String>, usize)> = None;
+    let mut dirty: bool = true;
+    curs_set(1);
+    win.timeout(POPUP_TIMEOUT_MS);
+    let answer: Option<String> = loop {
+        // An interrupt only cancels the prompt
+        if INTERRUPTED.swap(false, Ordering::SeqCst) { break None }
+        if dirty {
+            let y: i32 = win.get_max_y()-1;
+            win.mv(y, 0);
+            win.clrtoeol();
+            win.addstr(format!("{}{}", label, text));
+            win.refresh();
+            dirty = false;
+        }
+        let input: Option<Input> = win.getch();
+        if input.is_some() { dirty = true; }
+        match input {
+            Some(Input::Character('\t')) | Some(Input::KeyBTab) if base.is_some() => {
+                let step: isize = if input == Some(Input::KeyBTab) { -1 } else { 1 };
+                match &mut completions {
+                    Some((matches, i)) => *i = (*i as isize + step).rem_euclid(matches.len() as isize) as usize,
+                    None => {
+                        let matches: Vec<String> = complete_path(base.unwrap(), &text);
+                        if matches.is_empty() { beep(); }
+                        else {
+                            let i: usize = if step < 0 { matches.len()-1 } else { 0 };
+                            completions = Some((matches, i));
+                        }
+                    }
+                }
+                if let Some((matches, i)) = &completions { text = matches[*i].clone(); }
+                continue;
+            }
+            None => {}
+            _ => completions = None,
+        }
+        match input {
+            Some(Input::Character('\x1b')) | Some(Input::Character('\x03')) => break None,
+            Some(Input::Character('\n')) | Some(Input::KeyEnter) => break Some(text),
+            Some(Input::KeyBackspace) | Some(Input::Character('\x08')) | Some(Input::Character('\x7f')) => { text.pop(); }
+            Some(Input::Character(c)) if !c.is_control() => text.push(c),
+            _ => {}
+        }
+    };
+    // The listing goes back to polling for keys
+    win.nodelay(true);
+    curs_set(0);
+    answer
+}
+
+/// Lists the directories `text` could be completed to, sorted naturally and ending with a separator
+///
+/// Hidden directories are only proposed when the typed name starts with a dot
+fn complete_path(base: &Path, text: &str) -> Vec<String> {
+    let (dir, prefix): (&str, &str) = match text.rfind(is_separator) {
+        Some(i) => text.split_at(i+1),
+        None => ("", text),
+    };
+    let Ok(entries) = fs::read_dir(base.join(dir)) else { return vec![] };
+    let mut names: Vec<String> = entries.flatten()
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| name.starts_with(prefix) && (prefix.starts_with('.') || !name.starts_with('.')))
+        .collect();
+    names.sort_by(|a, b| natural_cmp(a, b));
+    names.into_iter().map(|name| format!("{}{}{}", dir, name, MAIN_SEPARATOR)).collect()
+}
+
+/// Asks a yes/no question on the last line of the screen, anything but `y` means no
+pub fn confirm(win: &Window, question: &str) -> bool {
+    let y: i32 = win.get_max_y()-1;
+    win.mv(y, 0);
+    win.clrtoeol();
+    win.addstr(format!("{} [y/N]", question));
+    win.refresh();
+    win.timeout(POPUP_TIMEOUT_MS);
+    let confirmed: bool = loop {
+        if INTERRUPTED.swap(false, Ordering::SeqCst) { break false }
+        match win.getch() {
+            Some(Input::Character('y')) | Some(Input::Character('Y')) => break true,
+            Some(Input::KeyMouse) | Some(Input::KeyResize) | None => {}
+            Some(_) => break false,
+        }
+    };
+    win.nodelay(true);
+    confirmed
+}
+
+/// Asks a yes/no question in a popup listing `lines`, anything but `y` means no
+pub fn confirm_list(win: &Window, question: &str, lines: &[String]) -> bool {
+    confirm_changing_list(win, question, || lines.to_vec())
+}
+
+/// Asks like [`confirm_list`], the lines are asked for again while waiting and drawn again when they change
+pub fn confirm_changing_list(win: &Window, question: &str, mut lines: impl FnMut() -> Vec<String>) -> bool {
+    let mut shown: Vec<String> = lines();
+    let (max_y, max_x) = win.get_max_yx();
+    let longest: i32 = shown.iter().map(|line| line.chars().count()).max().unwrap_or(0).max(question.chars().count() + 6) as i32;
+    let width: i32 = (longest + 4).min(max_x - 2).max(8);
+    let height: i32 = (shown.len() as i32 + 4).min(max_y - 2).max(5);
+    let popup: Window = newwin(height, width, (max_y - height) / 2, (max_x - width) / 2);
+    popup.keypad(true);
+    popup.timeout(POPUP_TIMEOUT_MS);
+    let fit = |text: &str| text.chars().take((width - 4).max(0) as usize).collect::<String>();
+    let mut dirty: bool = true;
+    let confirmed: bool = loop {
+        if INTERRUPTED.swap(false, Ordering::SeqCst) { break false }
+        if dirty {
+            popup.erase();
+            popup.draw_box(0, 0);
+            popup.mvaddstr(0, 2, fit(question));
+            for (row, line) in shown.iter().take((height - 4).max(0) as usize).enumerate() {
+                popup.mvaddstr(row as i32 + 1, 2, fit(line));
+            }
+            popup.attron(A_BOLD);
+            popup.mvaddstr(height - 2, 2, fit("[y/N]"));
+            popup.attroff(A_BOLD);
+            popup.refresh();
+            dirty = false;
+        }
+        match popup.getch() {
+            Some(Input::Character('y')) | Some(Input::Character('Y')) => break true,
+            Some(Input::KeyMouse) | Some(Input::KeyResize) => dirty = true,
+            Some(_) => break false,
+            None => {
+                let current: Vec<String> = lines();
+                if current != shown {
+                    shown = current;
+                    dirty = true;
+                }
+            }
+        }
+    };
+    popup.delwin();
+    confirmed
+}
+
+/// Lets the user pick one of `items` from a list drawn over the screen
+///
+/// Returns the index of the picked item, or `None` if the user cancelled with Escape or `Ctrl-C`
+pub fn pick(win: &Window, title: &str, items: &[String]) -> Option<usize> {
+    let (max_y, max_x) = win.get_max_yx();
+    let longest: i32 = items.iter().map(|item| item.chars().count()).max().unwrap_or(0).max(title.chars().count()) as i32;
+    let width: i32 = (longest + 4).min(max_x - 2).max(8);
+    let height: i32 = (items.len() as i32 + 2).min(max_y - 2).max(3);
+    let rows: i32 = height - 2;
+    let popup: Window = newwin(height, width, (max_y - height) / 2, (max_x - width) / 2);
+    popup.keypad(true);
+    // Nothing changes on its own, the popup only wakes up for keys and interrupts
+    popup.timeout(POPUP_TIMEOUT_MS);
+    let mut selected: i32 = 0;
+    let mut scroll: i32 = 0;
+    let mut dirty: bool = true;
+    let picked: Option<usize> = loop {
+        if INTERRUPTED.swap(false, Ordering::SeqCst) { break None }
+        selected = selected.clamp(0, (items.len() as i32 - 1).max(0));
+        if selected < scroll { scroll = selected; }
+        if selected >= scroll + rows { scroll = selected - rows + 1; }
+        if dirty {
+            popup.erase();
+            popup.draw_box(0, 0);
+            popup.mvaddstr(0, 2, title.chars().take((width - 4).max(0) as usize).collect::<String>());
+            for row in 0..rows {
+                let Some(item) = items.get((row + scroll) as usize) else { break };
+                if row + scroll == selected { popup.attron(A_REVERSE); }
+                popup.mvaddstr(row + 1, 2, item.chars().take((width - 4).max(0) as usize).collect::<String>());
+                if row + scroll == selected { popup.attroff(A_REVERSE); }
+            }
+            popup.refresh();
+            dirty = false;
+        }
+        let input: Option<Input> = popup.getch();
+        if input.is_some() { dirty = true; }
+        match input {
+            Some(Input::KeyUp) => selected -= 1,
+            Some(Input::KeyDown) => selected += 1,
+            Some(Input::KeyPPage) => selected -= rows,
+            Some(Input::KeyNPage) => selected += rows,
+            Some(Input::Character('\n')) | Some(Input::KeyEnter) if !items.is_empty() => break Some(selected as usize),
+            Some(Input::Character('\x1b')) | Some(Input::Character('\x03')) | Some(Input::Character('q')) => break None,
+            _ => {}
+        }
+    };
+    popup.delwin();
+    picked
+}
+
+/// Lets the user pick a directory of `index` in a popup, the typed text narrows them down from the best match
+///
+/// Returns the path of the picked directory, or `None` if the user cancelled with Escape or `Ctrl-C`
+pub fn fuzzy_pick(win: &Window, index: &DirIndex) -> Option<PathBuf> {
+    let (max_y, max_x) = win.get_max_yx();
+    let width: i32 = (max_x - 4).max(8);
+    let height: i32 = (max_y - 2).max(4);
+    let rows: i32 = height - 3;
+    let popup: Window = newwin(height, width, (max_y - height) / 2, (max_x - width) / 2);
+    popup.keypad(true);
+    // Also wakes up for the index filling up
+    popup.timeout(POPUP_TIMEOUT_MS);
+    let fit = |text: &str| text.chars().take((width - 4).max(0) as usize).collect::<String>();
+    let mut query: String = String::new();
+    // The best matches with what they were found for, the query and how many directories there were
+    let mut matches: Vec<String> = vec![];
+    let mut found_for: Option<(String, usize)> = None;
+    let mut selected: i32 = 0;
+    let mut scroll: i32 = 0;
+    let mut dirty: bool = true;
+    curs_set(1);
+    let picked: Option<PathBuf> = loop {
+        if INTERRUPTED.swap(false, Ordering::SeqCst) { break None }
+        // The index may still be filling up
+        let (total, building): (usize, bool) = (index.with_dirs(|dirs| dirs.len()), index.is_building());
+        if found_for.as_ref() != Some(&(query.clone(), total)) {
+            index.with_dirs(|dirs| {
+                let mut best: Vec<(i64, String)> = dirs.iter()
+                    .filter_map(|dir| fuzzy_score(dir, &query).map(|score| (score, dir.clone())))
+                    .collect();
+                best.sort_by(|(a_score, a), (b_score, b)| b_score.cmp(a_score).then_with(|| natural_cmp(a, b)));
+                matches = best.into_iter().map(|(_, dir)| dir).collect();
+            });
+            found_for = Some((query.clone(), total));
+            selected = 0;
+            dirty = true;
+        }
+        selected = selected.clamp(0, (matches.len() as i32 - 1).max(0));
+        if selected < scroll { scroll = selected; }
+        if selected >= scroll + rows { scroll = selected - rows + 1; }
+        if dirty {
+            popup.erase();
+            popup.draw_box(0, 0);
+            let indexing: &str = if building { ", indexing\u{2026}" } else { "" };
+            popup.mvaddstr(0, 2, fit(&format!("jump to ({}/{}{})", matches.len(), total, indexing)));
+            for row in 0..rows {
+                let Some(dir) = matches.get((row + scroll) as usize) else { break };
+                if row + scroll == selected { popup.attron(A_REVERSE); }
+                popup.mvaddstr(row + 2, 2, fit(dir));
+                if row + scroll == selected { popup.attroff(A_REVERSE); }
+            }
+            popup.mvaddstr(1, 2, fit(&format!("> {}", query)));
+            popup.refresh();
+            dirty = false;
+        }
+        let input: Option<Input> = popup.getch();
+        if input.is_some() { dirty = true; }
+        match input {
+            Some(Input::KeyUp) => selected -= 1,
+            Some(Input::KeyDown) => selected += 1,
+            Some(Input::KeyPPage) => selected -= rows,
+            Some(Input::KeyNPage) => selected += rows,
+            Some(Input::Character('\n')) | Some(Input::KeyEnter) => {
+                if let Some(dir) = matches.get(selected as usize) { break Some(index.root().join(dir)) }
+            }
+            Some(Input::Character('\x1b')) | Some(Input::Character('\x03')) => break None,
+            Some(Input::KeyBackspace) | Some(Input::Character('\x08')) | Some(Input::Character('\x7f')) => { query.pop(); }
+            Some(Input::Character(c)) if !c.is_control() => query.push(c),
+            _ => {}
+        }
+    };
+    curs_set(0);
+    popup.delwin();
+    picked
+}
+
+/// Lets the user toggle the nine permission bits of a Unix mode in a popup
+///
+/// Returns the edited permission bits, or `None` if the user cancelled with Escape or `Ctrl-C`
+#[cfg(unix)]
+pub fn edit_mode(win: &Window, title: &str, mode: u32) -> Option<u32> {
+    use crate::format::mode_string;
+    let (max_y, max_x) = win.get_max_yx();
+    let width: i32 = (title.chars().count() as i32 + 4).max(24).min(max_x - 2);
+    let popup: Window = newwin(5, width, (max_y - 5) / 2, (max_x - width) / 2);
+    popup.keypad(true);
+    popup.timeout(POPUP_TIMEOUT_MS);
+    let mut bits: u32 = mode & 0o777;
+    // Which bit is under the cursor, from the left
+    let mut cursor: u32 = 0;
+    let mut dirty: bool = true;
+    let edited: Option<u32> = loop {
+        if INTERRUPTED.swap(false, Ordering::SeqCst) { break None }
+        if dirty {
+            popup.erase();
+            popup.draw_box(0, 0);
+            popup.mvaddstr(0, 2, title.chars().take((width - 4).max(0) as usize).collect::<String>());
+            popup.mv(2, 2);
+            for (i, c) in mode_string(bits).chars().enumerate() {
+                if i as u32 == cursor { popup.attron(A_REVERSE); }
+                popup.addstr(c.to_string());
+                if i as u32 == cursor { popup.attroff(A_REVERSE); }
+            }
+            popup.addstr(format!("   {:03o}", bits));
+            popup.attron(A_DIM);
+            popup.mvaddstr(3, 2, "space toggles".chars().take((width - 4).max(0) as usize).collect::<String>());
+            popup.attroff(A_DIM);
+            popup.refresh();
+            dirty = false;
+        }
+        let input: Option<Input> = popup.getch();
+        if input.is_some() { dirty = true; }
+        match input {
+            Some(Input::KeyLeft) => cursor = cursor.saturating_sub(1),
+            Some(Input::KeyRight) => cursor = (cursor + 1).min(8),
+            Some(Input::Character(' ')) => bits ^= 1 << (8 - cursor),
+            Some(Input::Character('\n')) | Some(Input::KeyEnter) => break Some(bits),
+            Some(Input::Character('\x1b')) | Some(Input::Character('\x03')) | Some(Input::Character('q')) => break None,
+            _ => {}
+        }
+    };
+    popup.delwin();
+    edited
+}
@@ -0,0 +1,106 @@
+use std::{
+    ops::Range,
+    path::PathBuf,
+};
+
+use crate::FileStat;
+
+/// An incremental search through the names of the listing
+pub struct Search {
+    /// What is looked for
+    pub query: String,
+    /// Whether the query is still being typed
+    pub editing: bool,
+    /// The directory that is searched, the search ends when leaving it
+    pub dir: PathBuf,
+    /// Where the cursor was when the search started, matches are looked for from there
+    pub origin: i32,
+    /// Whether the case of the query has to match
+    pub case_sensitive: bool,
+}
+
+impl Search {
+
+    pub fn new(dir: PathBuf, origin: i32) -> Self {
+        Search {
+            query: String::new(),
+            editing: true,
+            dir,
+            origin,
+            case_sensitive: false,
+        }
+    }
+
+    /// Finds the query in `name`, see [`find_match`]
+    pub fn find(&self, name: &str) -> Option<Range<usize>> {
+        find_match(name, &self.query, self.case_sensitive)
+    }
+
+    /// Returns the index of the first entry matching the query, starting from the origin and wrapping around
+    pub fn first_match(&self, filez: &[FileStat]) -> Option<usize> {
+        let origin: usize = self.origin.max(0) as usize;
+        (origin..filez.len()).chain(0..origin.min(filez.len()))
+            .find(|&i| self.find(filez[i].file_name()).is_some())
+    }
+
+    /// Returns the indices of every entry matching the query, in the order of the listing
+    pub fn matches(&self, filez: &[FileStat]) -> Vec<usize> {
+        (0..filez.len()).filter(|&i| self.find(filez[i].file_name()).is_some()).collect()
+    }
+
+    /// Returns the index of the match after `from` (or before it when going `back`), wrapping around
+    pub fn next_match(&self, filez: &[FileStat], from: usize, back: bool) -> Option<usize> {
+        let matches: Vec<usize> = self.matches(filez);
+        if back {
+            matches.iter().rev().find(|&&i| i < from).or(matches.last()).copied()
+        }
+        else {
+            matches.iter().find(|&&i| i > from).or(matches.first()).copied()
+        }
+    }
+
+}
+
+/// Finds `query` in `name`, ignoring case unless `case_sensitive`, returns the byte range of the match in `name`
+pub fn find_match(name: &str, query: &str, case_sensitive: bool) -> Option<Range<usize>> {
+    if query.is_empty() { return None; }
+    let same = |c: char, q: char| if case_sensitive { c == q } else { c.to_lowercase().eq(q.to_lowercase()) };
+    'start: for (start, _) in name.char_indices() {
+        let mut rest = name[start..].char_indices();
+        let mut end: usize = start;
+        for q in query.chars() {
+            match rest.next() {
+                Some((i, c)) if same(c, q) => end = start + i + c.len_utf8(),
+                _ => continue 'start,
+            }
+        }
+        return Some(start..end);
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_ignore_case_by_default() {
+        assert_eq!(find_match("ReadMe.md", "readme", false), Some(0..6));
+        assert_eq!(find_match("notes.TXT", "txt", false), Some(6..9));
+        assert_eq!(find_match("notes.txt", "md", false), None);
+    }
+
+    #[test]
+    fn matches_can_respect_case() {
+        assert_eq!(find_match("ReadMe.md", "readme", true), None);
+        assert_eq!(find_match("readme ReadMe", "ReadMe", true), Some(7..13));
+        assert_eq!(find_match("Makefile", "Make", true), Some(0..4));
+    }
+
+    #[test]
+    fn ranges_are_in_bytes() {
+        assert_eq!(find_match("été.txt", "TÉ", false), Some(2..5));
+        assert_eq!(find_match("anything", "", false), None);
+        assert_eq!(find_match("ab", "abc", false), None);
+    }
+}
@@ -0,0 +1,215 @@
+use std::{
+    cmp::Ordering,
+    str::FromStr,
+};
+
+use crate::{is_hidden, FileStat};
+use crate::format::split_extension;
+
+/// The order in which the entries of a directory are listed, within the groups of [`GroupOrder`]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SortMode {
+    /// By name, numbers inside names are compared by value (`file2` before `file10`)
+    Natural,
+    /// By name, character by character
+    Name,
+    /// Biggest files first
+    Size,
+    /// Most recently modified first
+    Modified,
+    /// By extension, entries without one coming first, then by name
+    Extension,
+}
+
+impl SortMode {
+    const ALL: &'static [SortMode] = &[SortMode::Natural, SortMode::Name, SortMode::Size, SortMode::Modified, SortMode::Extension];
+
+    /// Returns the mode that comes after this one when cycling through them
+    pub fn next(self) -> SortMode {
+        let i: usize = SortMode::ALL.iter().position(|&mode| mode == self).unwrap();
+        SortMode::ALL[(i+1) % SortMode::ALL.len()]
+    }
+
+    /// Returns the name of the mode, as accepted by `--sort`
+    pub fn name(self) -> &'static str {
+        match self {
+            SortMode::Natural => "natural",
+            SortMode::Name => "name",
+            SortMode::Size => "size",
+            SortMode::Modified => "modified",
+            SortMode::Extension => "extension",
+        }
+    }
+
+    /// Compares two entries of the same group
+    fn cmp(self, a: &FileStat, b: &FileStat) -> Ordering {
+        match self {
+            SortMode::Natural => natural_cmp(a.file_name(), b.file_name()),
+            SortMode::Name => a.file_name().cmp(b.file_name()),
+            SortMode::Size => b.size().cmp(&a.size()).then_with(|| natural_cmp(a.file_name(), b.file_name())),
+            SortMode::Modified => b.modified().cmp(&a.modified()).then_with(|| natural_cmp(a.file_name(), b.file_name())),
+            SortMode::Extension => {
+                let (a_ext, b_ext) = (split_extension(a.file_name()).1, split_extension(b.file_name()).1);
+                a_ext.to_lowercase().cmp(&b_ext.to_lowercase()).then_with(|| natural_cmp(a.file_name(), b.file_name()))
+            }
+        }
+    }
+}
+
+impl FromStr for SortMode {
+    type Err = ();
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        SortMode::ALL.iter().copied().find(|mode| mode.name() == name).ok_or(())
+    }
+}
+
+/// Where hidden entries (dotfiles) go among the directories and among the files
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Dotfiles {
+    /// Sorted along with the other entries
+    Mixed,
+    /// Before the other entries
+    First,
+    /// After the other entries
+    Last,
+}
+
+impl Dotfiles {
+    const ALL: &'static [Dotfiles] = &[Dotfiles::Mixed, Dotfiles::Last, Dotfiles::First];
+
+    /// Returns the placement that comes after this one when cycling through them
+    pub fn next(self) -> Dotfiles {
+        let i: usize = Dotfiles::ALL.iter().position(|&dotfiles| dotfiles == self).unwrap();
+        Dotfiles::ALL[(i+1) % Dotfiles::ALL.len()]
+    }
+
+    /// Returns the name of the placement, as accepted by `--dotfiles`
+    pub fn name(self) -> &'static str {
+        match self {
+            Dotfiles::Mixed => "mixed",
+            Dotfiles::First => "first",
+            Dotfiles::Last => "last",
+        }
+    }
+
+    /// Compares two entries of the same group by whether they are hidden
+    fn cmp(self, a: &FileStat, b: &FileStat) -> Ordering {
+        let (a_hidden, b_hidden) = (is_hidden(a.file_name()), is_hidden(b.file_name()));
+        match self {
+            Dotfiles::Mixed => Ordering::Equal,
+            Dotfiles::First => b_hidden.cmp(&a_hidden),
+            Dotfiles::Last => a_hidden.cmp(&b_hidden),
+        }
+    }
+}
+
+impl FromStr for Dotfiles {
+    type Err = ();
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Dotfiles::ALL.iter().copied().find(|dotfiles| dotfiles.name() == name).ok_or(())
+    }
+}
+
+/// Whether directories and files are listed apart
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GroupOrder {
+    /// Directories before files
+    DirsFirst,
+    /// Files before directories
+    FilesFirst,
+    /// Directories sorted along with files
+    Mixed,
+}
+
+impl GroupOrder {
+    const ALL: &'static [GroupOrder] = &[GroupOrder::DirsFirst, GroupOrder::FilesFirst, GroupOrder::Mixed];
+
+    /// Returns the name of the grouping, as accepted by `--group-order`
+    pub fn name(self) -> &'static str {
+        match self {
+            GroupOrder::DirsFirst => "dirs-first",
+            GroupOrder::FilesFirst => "files-first",
+            GroupOrder::Mixed => "mixed",
+        }
+    }
+
+    /// Compares two entries by whether they are directories
+    fn cmp(self, a: &FileStat, b: &FileStat) -> Ordering {
+        match self {
+            GroupOrder::DirsFirst => b.is_dir().cmp(&a.is_dir()),
+            GroupOrder::FilesFirst => a.is_dir().cmp(&b.is_dir()),
+            GroupOrder::Mixed => Ordering::Equal,
+        }
+    }
+}
+
+impl FromStr for GroupOrder {
+    type Err = ();
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        GroupOrder::ALL.iter().copied().find(|groups| groups.name() == name).ok_or(())
+    }
+}
+
+/// Sorts the entries of a listing, grouped according to `groups`, then with dotfiles placed according to `dotfiles`,
+/// `reverse` flips the order within each group
+pub fn sort_entries(filez: &mut [FileStat], mode: SortMode, reverse: bool, dotfiles: Dotfiles, groups: GroupOrder) {
+    filez.sort_by(|a: &FileStat, b: &FileStat| groups.cmp(a, b).then_with(|| dotfiles.cmp(a, b)).then_with(|| {
+        let ordering: Ordering = mode.cmp(a, b);
+        if reverse { ordering.reverse() } else { ordering }
+    }));
+}
+
+/// Compares names so that the numbers they contain are ordered by value, letters are compared case-insensitively
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a_chars, mut b_chars) = (a.chars().peekable(), b.chars().peekable());
+    loop {
+        match (a_chars.peek().copied(), b_chars.peek().copied()) {
+            (None, None) => return a.cmp(b),
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(ca), Some(cb)) if ca.is_ascii_digit() && cb.is_ascii_digit() => {
+                let take_number = |chars: &mut std::iter::Peekable<std::str::Chars>| {
+                    let mut digits: String = String::new();
+                    while let Some(c) = chars.next_if(char::is_ascii_digit) { digits.push(c); }
+                    digits
+                };
+                let (na, nb) = (take_number(&mut a_chars), take_number(&mut b_chars));
+                let (ta, tb) = (na.trim_start_matches('0'), nb.trim_start_matches('0'));
+                // Without leading zeros, a longer number is a bigger one
+                let ordering: Ordering = ta.len().cmp(&tb.len()).then_with(|| ta.cmp(tb));
+                if ordering != Ordering::Equal { return ordering; }
+            }
+            (Some(ca), Some(cb)) => {
+                let ordering: Ordering = ca.to_lowercase().cmp(cb.to_lowercase());
+                if ordering != Ordering::Equal { return ordering; }
+                a_chars.next();
+                b_chars.next();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbers_are_ordered_by_value() {
+        let mut names: Vec<&str> = vec!["file10", "file2", "file1"];
+        names.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(names, ["file1", "file2", "file10"]);
+        assert_eq!(natural_cmp("v1.10", "v1.9"), Ordering::Greater);
+        assert_eq!(natural_cmp("img007", "img7"), Ordering::Less);
+        assert_eq!(natural_cmp("99999999999999999999999", "100000000000000000000000"), Ordering::Less);
+    }
+
+    #[test]
+    fn letters_ignore_case() {
+        assert_eq!(natural_cmp("apple", "Banana"), Ordering::Less);
+        assert_eq!(natural_cmp("a2b", "A10a"), Ordering::Less);
+        assert_eq!(natural_cmp("abc", "abc1"), Ordering::Less);
+        assert_eq!(natural_cmp("x1y", "x1z"), Ordering::Less);
+        // Names that only differ by case still get an order
+        assert_ne!(natural_cmp("readme", "README"), Ordering::Equal);
+    }
+}
@@ -0,0 +1,88 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    time::SystemTime,
+};
+
+use crate::FileStat;
+
+/// How deep directories can be expanded, which also stops symbolic link loops
+const MAX_DEPTH: usize = 32;
+
+/// The state of the tree view, where the contents of expanded directories are shown under them
+#[derive(Default)]
+pub struct Tree {
+    /// Whether the listing is shown as a tree
+    pub enabled: bool,
+    /// Directories are expanded up to this depth unless collapsed
+    pub depth: usize,
+    expanded: HashSet<String>,
+    collapsed: HashSet<String>,
+    children: HashMap<String, (Option<SystemTime>, Vec<FileStat>)>,
+}
+
+impl Tree {
+
+    pub fn new(enabled: bool, depth: usize) -> Self {
+        Tree {
+            enabled,
+            depth,
+            ..Tree::default()
+        }
+    }
+
+    /// Returns whether the directory at `path`, which is `level` directories deep, shows its contents
+    pub fn is_expanded(&self, path: &str, level: usize) -> bool {
+        level < MAX_DEPTH && (self.expanded.contains(path) || (level < self.depth && !self.collapsed.contains(path)))
+    }
+
+    pub fn expand(&mut self, path: &str) {
+        self.collapsed.remove(path);
+        self.expanded.insert(path.to_string());
+    }
+
+    pub fn collapse(&mut self, path: &str) {
+        self.expanded.remove(path);
+        self.collapsed.insert(path.to_string());
+    }
+
+    /// Puts the contents of the expanded directories of `filez` right after them,
+    /// returns the entries along with how deep each one is
+    ///
+    /// The contents of a directory are read the first time it is expanded, and again once it gets modified,
+    /// `sort` puts them in the order of the listing and only those `listed` keeps are shown
+    pub fn flatten(&mut self, filez: Vec<FileStat>, sort: &dyn Fn(&mut [FileStat]), listed: &dyn Fn(&FileStat) -> bool) -> (Vec<FileStat>, Vec<usize>) {
+        let mut flat: (Vec<FileStat>, Vec<usize>) = (vec![], vec![]);
+        self.push_level(&mut flat, filez, 0, sort, listed);
+        flat
+    }
+
+    fn push_level(&mut self, flat: &mut (Vec<FileStat>, Vec<usize>), filez: Vec<FileStat>, level: usize, sort: &dyn Fn(&mut [FileStat]), listed: &dyn Fn(&FileStat) -> bool) {
+        for entry in filez {
+            let expanded: bool = entry.is_dir() && self.is_expanded(entry.path(), level);
+            let path: String = entry.path().to_string();
+            flat.0.push(entry);
+            flat.1.push(level);
+            if expanded {
+                let mut children: Vec<FileStat> = self.children(&path);
+                children.retain(listed);
+                sort(&mut children);
+                self.push_level(flat, children, level+1, sort, listed);
+            }
+        }
+    }
+
+    /// Returns the entries of the directory at `path`
+    fn children(&mut self, path: &str) -> Vec<FileStat> {
+        let modified: Option<SystemTime> = fs::metadata(path).and_then(|meta| meta.modified()).ok();
+        let cached = self.children.entry(path.to_string()).or_insert((None, vec![]));
+        if cached.0.is_none() || cached.0 != modified {
+            let entries: Vec<FileStat> = fs::read_dir(path)
+                .map(|entries| entries.flatten().map(FileStat::from).collect())
+                .unwrap_or_default();
+            *cached = (modified, entries);
+        }
+        cached.1.clone()
+    }
+
+}